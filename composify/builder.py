@@ -1,11 +1,12 @@
 """Implementation of Builder and AsyncBuilder to build using solution."""
 
 import asyncio
+from collections.abc import Awaitable
 from concurrent.futures import ThreadPoolExecutor
 from functools import partial
 from typing import Any, TypeVar
 
-from composify.core import Solution
+from composify.core import AsyncResolution, Solution
 from composify.errors import AsyncSolutionError
 
 __all__ = [
@@ -77,7 +78,7 @@ class AsyncBuilder:
         parameters = dict(zip(names, results, strict=True))
 
         if asyncio.iscoroutinefunction(solution.function):
-            value = await solution(**parameters)
+            value = await solution.function(**parameters)
         elif self._threadpool_executor is not None:
             loop = asyncio.get_running_loop()
             value = await loop.run_in_executor(
@@ -134,3 +135,21 @@ class Builder:
         value = solution.function(**parameters)
 
         return value
+
+
+async def resolve_awaiting_qualifiers(
+    resolution: AsyncResolution, pending: Awaitable[bool] | None
+) -> Any:
+    """Await the async qualifiers a resolution stops at, resuming it with
+    each result until it is solved, then build the solution.
+
+    Args:
+        resolution (AsyncResolution): The resolution to drive.
+        pending (Awaitable[bool] | None): The qualifier it stopped at.
+
+    Returns:
+        Any: The built object.
+    """
+    while pending is not None:
+        pending = resolution.resume(await pending)
+    return await resolution.build()
@@ -1,25 +1,134 @@
-from collections.abc import Callable, Iterable, Iterator, Mapping, Sequence
+from collections.abc import (
+    Awaitable,
+    Callable,
+    Iterable,
+    Iterator,
+    Mapping,
+    Sequence,
+)
 from enum import Enum, auto
 from typing import Any, overload
 
 class MetadataSet:
-    def __new__(items: Iterable[Any]): ...
+    def __new__(items: Iterable[Any], single_valued: bool = False): ...
     def __hash__(self): ...
+    def __eq__(self, other: object) -> bool: ...
+    def __iter__(self) -> Iterator[Any]: ...
     def __getitem__(self, key: type) -> Any: ...
+    def __contains__(self, key: object) -> bool: ...
+    def __len__(self) -> int: ...
     def get(self, key: type) -> Any | None: ...
+    def get_all(self, key: type) -> list[Any]: ...
     def issuperset(self, other: MetadataSet) -> bool: ...
     def issubset(self, other: MetadataSet) -> bool: ...
+    def union(self, other: MetadataSet) -> MetadataSet: ...
+    def intersection(self, other: MetadataSet) -> MetadataSet: ...
+    def difference(self, other: MetadataSet) -> MetadataSet: ...
+    def filter(self, predicate: Callable[[Any], bool]) -> MetadataSet: ...
+
+class Not:
+    """Qualifier passing when the wrapped qualifier fails."""
+
+    def __new__(cls, qualifier: Any): ...
+    def qualify(self, attributes: MetadataSet) -> bool: ...
+    def qualify_for(
+        self, attributes: MetadataSet, requested: TypeInfo | None = None
+    ) -> bool: ...
+    def __hash__(self): ...
+
+class QualifierMode(Enum):
+    """Determine how qualifiers are combined:
+    - All: Qualified if all qualifiers pass.
+    - Any: Qualified if any qualifier passes.
+    """
+
+    All = auto()
+    Any = auto()
 
 class Qualifiers:
-    def __new__(items: Iterable[Any]): ...
+    mode: QualifierMode
+
+    def __new__(
+        items: Iterable[Any], mode: QualifierMode = QualifierMode.All
+    ): ...
+    @staticmethod
+    def all(items: Iterable[Any]) -> Qualifiers: ...
+    @staticmethod
+    def any(items: Iterable[Any]) -> Qualifiers: ...
     def __hash__(self): ...
     def __repr__(self): ...
-    def qualify(self, attributes: MetadataSet) -> bool: ...
+    def __and__(self, other: Any) -> Qualifiers: ...
+    def __or__(self, other: Any) -> Qualifiers: ...
+    def qualify(
+        self, attributes: MetadataSet, requested: TypeInfo | None = None
+    ) -> bool: ...
+    def qualify_for(
+        self, attributes: MetadataSet, requested: TypeInfo | None = None
+    ) -> bool: ...
+
+class OnDuplicate(Enum):
+    """Determine what registering a rule equal to a registered one, and
+    wrapping the same function, does:
+    - Ignore: Keep the registered rule and skip the new one.
+    - Error: Raise a DuplicateRuleError.
+    - Allow: Register the rule again.
+    """
+
+    Ignore = auto()
+    Error = auto()
+    Allow = auto()
 
 class RuleRegistry:
+    """Output types without local rules are looked up in the parent registry.
+    The parent is snapshotted when the child is created, rules added to or
+    removed from the parent afterwards are not seen by the child.
+    """
+
+    def __new__(
+        cls,
+        parent: RuleRegistry | None = None,
+        merge_parent: bool = False,
+        cache_qualifiers: bool = False,
+        on_duplicate: OnDuplicate = OnDuplicate.Ignore,
+    ): ...
+    @property
+    def parent(self) -> RuleRegistry | None: ...
     def add_rule(self, rule: Rule) -> None: ...
     def add_rules(self, rules: Iterable[Rule]) -> None: ...
-    def get_rules(self, type_info: type) -> Sequence[Rule]: ...
+    def merge(self, other: RuleRegistry) -> None: ...
+    def remove_rule(self, rule: Rule) -> bool: ...
+    def clear(self) -> None: ...
+    def reindex(self) -> None: ...
+    def distance(self, requested: Any, provided: Any) -> int | None: ...
+    def is_assignable(
+        self,
+        provided: Any,
+        requested: Any,
+        specificity: SolveSpecificity | None = None,
+    ) -> bool: ...
+    def validate(self) -> list[tuple[str, str, TypeInfo, str]]: ...
+    def contains(self, type_info: Any) -> bool: ...
+    def rule_count_for(self, type_info: Any) -> int: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Rule]: ...
+    def rules_with_tag(self, tag: str) -> list[Rule]: ...
+    def dependents_of(self, type_info: Any) -> list[Rule]: ...
+    def get_rules(
+        self, type_info: type, prefer_attributes: Iterable[Any] | None = None
+    ) -> Sequence[Rule]: ...
+
+class NamingScheme(Enum):
+    """Determine how canonical type names are rendered:
+    - Short: `module.Name`, omitting the module for builtins.
+    - FullyQualified: Always `module.Name`.
+    """
+
+    Short = auto()
+    FullyQualified = auto()
+
+def set_canonical_name_strategy(
+    strategy: NamingScheme | Callable[[type], str] | None = None,
+) -> None: ...
 
 class TypeInfo:
     type_name: str
@@ -29,21 +138,41 @@ class TypeInfo:
     attributes: MetadataSet
     qualifiers: Qualifiers
     solve_parameter: SolveParameter
+    element: TypeInfo | None
+    optional: bool
+    type_args: tuple[Any, ...]
 
     def __new__(type_info: type, metadata: Sequence[Any]): ...
     @staticmethod
     def parse(any_type: Any) -> TypeInfo: ...
+    @property
+    def canonical_name(self) -> str: ...
+    def is_builtin(self) -> bool: ...
+    def with_metadata(self, items: Sequence[Any]) -> TypeInfo: ...
+    def with_solve_parameter(
+        self, solve_parameter: SolveParameter
+    ) -> TypeInfo: ...
     def __hash__(self): ...
     def __repr__(self): ...
 
 class Dependency:
     name: str
     typing: TypeInfo
+    variadic: bool
+    optional: bool
 
+    def __new__(cls, name: str, typing: Any | TypeInfo): ...
     def __hash__(self): ...
 
 class Dependencies:
+    """The dependencies of a rule, iterated in order of their names."""
+
+    def __new__(cls, parameters: Mapping[str, Any]): ...
     def __iter__(self) -> Iterator[Dependency]: ...
+    def __len__(self) -> int: ...
+    def __contains__(self, name: str) -> bool: ...
+    def __getitem__(self, name: str) -> Dependency: ...
+    def get(self, name: str) -> Dependency | None: ...
     def __hash__(self): ...
 
 class Rule:
@@ -53,6 +182,12 @@ class Rule:
     dependencies: Dependencies
     priority: int
     is_async: bool
+    guard: Callable[[list[TypeInfo]], bool] | None
+    tags: frozenset[str]
+    enabled: Callable[[], bool] | None
+    module: str | None
+    qualname: str | None
+    lineno: int | None
 
     def __new__(
         function: Callable,
@@ -61,39 +196,98 @@ class Rule:
         dependencies: Mapping[str, type],
         priority: int,
         is_async: bool,
+        guard: Callable[[list[TypeInfo]], bool] | None = None,
+        tags: Iterable[str] | None = None,
+        enabled: Callable[[], bool] | None = None,
     ): ...
     def __hash__(self): ...
+    def with_priority(self, priority: int) -> Rule: ...
+    def with_canonical_name(self, canonical_name: str) -> Rule: ...
+    def with_tags(self, tags: Iterable[str]) -> Rule: ...
+    def with_guard(
+        self, guard: Callable[[list[TypeInfo]], bool] | None
+    ) -> Rule: ...
+    def with_enabled(self, enabled: Callable[[], bool] | None) -> Rule: ...
+    def is_satisfiable_by(self, registry: RuleRegistry) -> bool: ...
+
+def make_rule(
+    function: Callable[..., Any],
+    *,
+    canonical_name: str | None = None,
+    output_type: Any | None = None,
+    priority: int = 0,
+    guard: Callable[[list[TypeInfo]], bool] | None = None,
+    tags: Iterable[str] | None = None,
+    enabled: Callable[[], bool] | None = None,
+) -> Rule: ...
 
 class SolveSpecificity(Enum):
     """Determine the specificity of the solutions' result types:
     - (=) Exact: Allow only for exact type. No superclasses or subclasses are allowed.
     - (+) AllowSubclass: Allow for solutions resulting in subclasses.
     - (-) AllowSuperclass: Allow for solutions resulting in superclasses.
+    - (~) AllowSubclassOrSuperclass: Allow for solutions resulting in either subclasses or superclasses.
     """
 
     Exact = auto()
     AllowSubclass = auto()
     AllowSuperclass = auto()
+    AllowSubclassOrSuperclass = auto()
 
 class SolveCardinality(Enum):
     """Determine the number of solutions to match when solving for a specific type:
     - (*) Exhaustive: Solve for all possible solution including all permutations of dependencies.
     - (1) Single: Solve for the first possible solution respecting the priority of rules, ignoring the rest of the solutions.
     - (x) Exclusive: Solve for a single possible solution. Raises error if there are multiple solutions including permutations of dependencies.
+    - (?) AtMostOne: Solve for zero or one solution. Returns no solution if nothing matches, raises error if there are multiple solutions.
     """
 
     Exhaustive = auto()
     Single = auto()
     Exclusive = auto()
+    AtMostOne = auto()
+
+class DependencyMarker(Enum):
+    """Markers changing how a dependency is bound, used as `Annotated` metadata:
+    - Variadic: Bind every solution of the type as a single list argument.
+    - Optional: Leave the argument out if the type cannot be resolved.
+    """
+
+    Variadic = auto()
+    Optional = auto()
+
+class First:
+    """Solve for the first n solutions respecting the priority of rules, n
+    must be at least one."""
+
+    n: int
+
+    def __new__(cls, n: int): ...
+
+class Strict:
+    """Solve for the highest priority solution, raising if another solution
+    has the same priority. Unlike Single, preferred attributes do not
+    outrank priority.
+    """
+
+    def __new__(cls): ...
 
 class SolveParameter:
     def __new__(
-        cls, specificity: SolveSpecificity, cardinality: SolveCardinality
+        cls,
+        specificity: SolveSpecificity,
+        cardinality: SolveCardinality,
+        limit: int | None = None,
+        strict: bool = False,
     ): ...
     @property
     def specificity(self) -> SolveSpecificity: ...
     @property
     def cardinality(self) -> SolveCardinality: ...
+    @property
+    def limit(self) -> int | None: ...
+    @property
+    def strict(self) -> bool: ...
 
 class SolutionArg:
     @property
@@ -108,6 +302,14 @@ class SolutionArgsCollection(Sequence[SolutionArg]):
     @overload
     def __new__(self, args: Mapping[str, Solution]): ...
     def __hash__(self): ...
+    @overload
+    def __getitem__(self, key: int) -> SolutionArg: ...
+    @overload
+    def __getitem__(self, key: str) -> SolutionArg: ...
+    def get(self, name: str) -> SolutionArg | None: ...
+    def keys(self) -> list[str]: ...
+    def values(self) -> list[Solution]: ...
+    def items(self) -> list[tuple[str, Solution]]: ...
 
 class Solution:
     def __new__(self, rule: Rule, args: Mapping[str, Solution] | None): ...
@@ -121,8 +323,114 @@ class Solution:
     def output_type(self) -> TypeInfo: ...
     @property
     def is_async(self) -> bool: ...
+    @property
+    def match_kind(self) -> SolveSpecificity | None: ...
+    @property
+    def is_unresolved(self) -> bool: ...
+    def equals_ignoring_args(self, other: Solution) -> bool: ...
+    def build_plan(self) -> list[Solution]: ...
+    def build(self, overrides: Mapping[str, Any] | None = None) -> Any: ...
+    def to_dict(self) -> dict[str, Any]: ...
+    def to_dot(self) -> str: ...
+    def fingerprint(self) -> str: ...
+    def score(self) -> int: ...
+    def depth(self) -> int: ...
+    def node_count(self, unique: bool = False) -> int: ...
     def __hash__(self): ...
 
+class Stats:
+    rules_evaluated: int
+    memo_hits: int
+    max_depth: int
+    permutations_generated: int
+
+class Explanation:
+    name: str
+    target: TypeInfo
+    errors: list[Exception]
+    rules: list[RuleAttempt]
+
+    def leaves(self) -> list[Explanation]: ...
+
+class RuleAttempt:
+    rule: str | None
+    dependencies: list[Explanation]
+
+class DependencyGraph:
+    edges: dict[TypeInfo, list[TypeInfo]]
+    cycles: list[tuple[TypeInfo, TypeInfo]]
+
+class ResolutionOrder(Enum):
+    """Determine how the solutions of an exhaustive target are ordered:
+    - DepthFirst: Closest output types first, then highest score.
+    - BreadthFirst: Shallowest solution trees first, then as DepthFirst.
+    """
+
+    DepthFirst = auto()
+    BreadthFirst = auto()
+
+class SolverOverrides:
+    def __enter__(self) -> Solver: ...
+    def __exit__(self, *args: Any) -> bool: ...
+
+class AsyncResolution:
+    def resume(self, qualified: Any) -> Awaitable[bool] | None: ...
+    def build(self) -> Awaitable[Any]: ...
+
 class Solver:
-    def __new__(rules: RuleRegistry): ...
-    def solve_for(self, type: Any) -> Sequence[Solution]: ...
+    def __new__(
+        rules: RuleRegistry,
+        max_depth: int | None = 64,
+        max_permutations: int | None = None,
+        memo_capacity: int | None = None,
+        trace_hook: Callable[[str, str, TypeInfo], Any] | None = None,
+        parallel: bool = False,
+        max_solutions: int | None = None,
+        truncate_solutions: bool = False,
+        resolution_order: ResolutionOrder = ResolutionOrder.DepthFirst,
+    ): ...
+    def add_rule(self, rule: Rule) -> None: ...
+    def invalidate(self, target: Any) -> None: ...
+    def clear_cache(self) -> None: ...
+    def set_policy(
+        self, type: Any, solve_parameter: SolveParameter | None
+    ) -> None: ...
+    def with_overrides(self, rules: Iterable[Rule]) -> SolverOverrides: ...
+    def dependency_graph(self, target: Any) -> DependencyGraph: ...
+    def preload(
+        self, targets: list[Any]
+    ) -> list[tuple[TypeInfo, Exception | None]]: ...
+    def unused_rules(self, targets: Sequence[Any]) -> list[Rule]: ...
+    def memo_stats(self, is_async: bool = False) -> dict[str, Any]: ...
+    def solve_for(
+        self,
+        type: Any,
+        prefer_attributes: Iterable[Any] | None = None,
+        max_preview_depth: int | None = None,
+        mocks: Mapping[Any, Solution | Rule | Any] | None = None,
+        filter: Callable[[Solution], bool] | None = None,
+        timeout: float | None = None,
+        max_visits: int | None = None,
+    ) -> Sequence[Solution]: ...
+    def solve_for_async(
+        self,
+        type: Any,
+        prefer_attributes: Iterable[Any] | None = None,
+        max_preview_depth: int | None = None,
+        mocks: Mapping[Any, Solution | Rule | Any] | None = None,
+        filter: Callable[[Solution], bool] | None = None,
+        timeout: float | None = None,
+        max_visits: int | None = None,
+    ) -> Sequence[Solution]: ...
+    def solve_many(self, targets: Sequence[Any]) -> list[list[Solution]]: ...
+    def solve_first(self, target: Any) -> Solution | None: ...
+    def explain(self, type: Any) -> Explanation | None: ...
+    def solve_for_with_stats(
+        self, type: Any
+    ) -> tuple[Sequence[Solution], Stats]: ...
+    def resolve_async(
+        self,
+        type: Any,
+        prefer_attributes: Iterable[Any] | None = None,
+        mocks: Mapping[Any, Solution | Rule | Any] | None = None,
+    ) -> Awaitable[Any]: ...
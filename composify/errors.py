@@ -24,16 +24,35 @@ class MissingParameterTypeAnnotation(InvalidTypeAnnotation):
     pass
 
 
-Trace: TypeAlias = tuple[str, TypeInfo]
+class DuplicateRuleError(ValueError):
+    """Raised when registering a rule equal to an already registered one."""
+
+    pass
+
+
+Trace: TypeAlias = tuple[str, TypeInfo, str | None, str | None]
+"""Dependency name, target type, the canonical name of the rule tried, and
+where that rule was defined as ``module:lineno``."""
 Traces: TypeAlias = Sequence[Trace]
 
 
+def _format_target(trace: Trace) -> str:
+    if trace[2] is None:
+        return str(trace[1])
+    if trace[3] is None:
+        return f"{trace[1]} by {trace[2]}"
+    return f"{trace[1]} by {trace[2]} ({trace[3]})"
+
+
 def _format_trace(trace: Trace) -> str:
-    return f"({trace[0]}: {trace[1]})"
+    return f"({trace[0]}: {_format_target(trace)})"
 
 
 def _format_traces(traces: Traces) -> str:
-    steps = [str(traces[0][1]), *(_format_trace(trace) for trace in traces[1:])]
+    steps = [
+        _format_target(traces[0]),
+        *(_format_trace(trace) for trace in traces[1:]),
+    ]
     return " -> ".join(steps)
 
 
@@ -47,6 +66,7 @@ class SolveFailureError(SolvingError):
     """Raised when no solutions are found."""
 
     def __init__(self, errors: Iterable[SolvingError]) -> None:
+        errors = list(errors)
         error_strings = tuple(
             f"- {_format_traces(error.traces)}: {error}"
             if isinstance(error, TracedSolvingError)
@@ -55,7 +75,7 @@ class SolveFailureError(SolvingError):
         )
         error_string = "\n".join(error_strings)
         super().__init__(f"Solving failure:\n{error_string}")
-        self.errors = errors
+        self.errors: list[SolvingError] = errors
 
     def contains(self, exc_type: type[SolvingError]) -> bool:
         """Checks if any exception raised was of a specific type.
@@ -81,32 +101,151 @@ class TracedSolvingError(SolvingError):
         super().__init__(msg)
         self.traces = traces
 
+    @property
+    def trace(self) -> list[Trace]:
+        """The steps leading to the error, starting from the root target."""
+        return list(self.traces)
+
+    @property
+    def target(self) -> TypeInfo | None:
+        """The type being solved when the error occurred."""
+        return self.traces[-1][1] if self.traces else None
+
+
+QualifierRejection: TypeAlias = tuple[str, str]
+"""The canonical name of a rule and why a qualifier rejected it."""
+
 
 class NoSolutionError(TracedSolvingError):
-    """Raised when there is no available solution."""
+    """Raised when there is no available solution. The rejections list the
+    rules that were excluded only by the qualifiers of the target.
+    """
 
-    def __init__(self, traces: Traces) -> None:
-        super().__init__(traces, "Unable to find solution.")
+    def __init__(
+        self,
+        traces: Traces,
+        rejections: Sequence[QualifierRejection] = (),
+    ) -> None:
+        self.rejections = tuple(rejections)
+        msg = "Unable to find solution."
+        if self.rejections:
+            reasons = "; ".join(
+                f"rule {rule} rejected by {reason}"
+                for rule, reason in self.rejections
+            )
+            msg = f"Unable to find solution, {reasons}."
+        super().__init__(traces, msg)
+
+
+def _format_cycle_hint(traces: Traces) -> str | None:
+    if len(traces) < 2 or traces[-2][2] is None:
+        return None
+    name, target = traces[-1][0], traces[-1][1]
+    return (
+        f"dependency '{name}' of rule {traces[-2][2]} closes the cycle, "
+        f"register a rule providing {target} outside of the cycle to break it"
+    )
 
 
 class CyclicDependencyError(TracedSolvingError):
-    """Raised when a cyclic dependency occurred in the dependency graph."""
+    """Raised when a cyclic dependency occurred in the dependency graph.
+    The cycle contains the traces from the first occurrence of the repeated
+    type, and the hint names the dependency edge that closes the cycle.
+    """
+
+    def __init__(self, traces: Traces, cycle_start: int = 0) -> None:
+        self.cycle = tuple(traces[cycle_start:])
+        self.path = " -> ".join(str(trace[1]) for trace in self.cycle)
+        self.hint = _format_cycle_hint(traces)
+        msg = f"Encountered cyclic dependency: {self.path}."
+        if self.hint is not None:
+            msg = f"{msg} Hint: {self.hint}."
+        super().__init__(traces, msg)
+
+
+class MaxDepthExceededError(TracedSolvingError):
+    """Raised when the dependency graph is deeper than the solver's max depth."""
 
     def __init__(self, traces: Traces) -> None:
-        super().__init__(traces, "Encountered cyclic dependency.")
+        super().__init__(
+            traces, f"Exceeded maximum depth of {len(traces) - 1}."
+        )
 
 
 class NotExclusiveError(TracedSolvingError):
     """Raised when a dependency contains multiple solution in Exclusive cardinality."""
 
     def __init__(self, solutions: Sequence[Solution], traces: Traces) -> None:
-        self.solutions = solutions
+        self.solutions = list(solutions)
+        self.conflicting_rules = [
+            solution.rule.canonical_name for solution in self.solutions
+        ]
+        target = traces[-1][1] if traces else None
+        super().__init__(
+            traces,
+            f"Found {len(self.solutions)} solutions for {target} from rules "
+            f"{', '.join(self.conflicting_rules)}: "
+            f"{', '.join(str(solution) for solution in self.solutions)}",
+        )
+
+
+class AmbiguousSingleError(TracedSolvingError):
+    """Raised when the highest priority is shared by multiple solutions in
+    strict Single cardinality.
+    """
+
+    def __init__(self, solutions: Sequence[Solution], traces: Traces) -> None:
+        self.solutions = list(solutions)
+        self.conflicting_rules = [
+            solution.rule.canonical_name for solution in self.solutions
+        ]
+        target = traces[-1][1] if traces else None
+        priority = self.solutions[0].rule.priority if self.solutions else None
         super().__init__(
             traces,
-            f"Found multiple solutions: {', '.join(str(solution) for solution in solutions)}",
+            f"Found {len(self.solutions)} solutions for {target} with the "
+            f"highest priority {priority} from rules "
+            f"{', '.join(self.conflicting_rules)}",
+        )
+
+
+class TooManyPermutationsError(TracedSolvingError):
+    """Raised when a rule has more argument permutations than the solver's limit."""
+
+    def __init__(self, count: int, traces: Traces) -> None:
+        self.count = count
+        super().__init__(
+            traces, f"Exceeded maximum permutations with {count} permutations."
         )
 
 
+class TooManySolutionsError(TracedSolvingError):
+    """Raised when an exhaustive target has more solutions than the solver's
+    limit.
+    """
+
+    def __init__(self, max_solutions: int, traces: Traces) -> None:
+        self.max_solutions = max_solutions
+        super().__init__(
+            traces, f"Exceeded maximum solutions of {max_solutions}."
+        )
+
+
+class SolveTimeoutError(TracedSolvingError):
+    """Raised when solving runs out of its time or node visit budget."""
+
+    def __init__(self, traces: Traces) -> None:
+        super().__init__(traces, "Exceeded the solving budget.")
+
+
+class AsyncQualifierError(SolvingError):
+    """Raised when an async qualifier is evaluated outside of
+    ``Solver.resolve_async``.
+    """
+
+    pass
+
+
 class BuilderError(Exception):
     """Base class for all Builder related errors."""
 
@@ -2,9 +2,14 @@
 
 from typing import Protocol
 
-from composify.core import MetadataSet
+from composify.core import MetadataSet, TypeInfo
 
-__all__ = ("MetadataSet", "Qualifier")
+__all__ = (
+    "ContextualQualifier",
+    "ExplainedQualifier",
+    "MetadataSet",
+    "Qualifier",
+)
 
 
 class Qualifier(Protocol):
@@ -15,3 +20,27 @@ class Qualifier(Protocol):
     def qualify(self, attributes: MetadataSet) -> bool:
         """Returns true if the set of attributes is qualified."""
         raise NotImplementedError()
+
+
+class ContextualQualifier(Protocol):
+    """Protocol for qualifiers that also depend on the requested type.
+    Preferred over `qualify` when both are implemented.
+    """
+
+    def qualify_for(
+        self, attributes: MetadataSet, requested: TypeInfo | None
+    ) -> bool:
+        """Returns true if the set of attributes is qualified for the
+        requested type, which is None when qualifying outside of solving.
+        """
+        raise NotImplementedError()
+
+
+class ExplainedQualifier(Qualifier, Protocol):
+    """Protocol for qualifiers describing their rejections, which are
+    reported when a solve fails. Other qualifiers are described by their repr.
+    """
+
+    def rejection_reason(self, attributes: MetadataSet) -> str:
+        """Returns why the set of attributes is not qualified."""
+        raise NotImplementedError()
@@ -21,7 +21,7 @@ from functools import partial, wraps
 from types import FrameType, ModuleType
 from typing import Annotated, Any, ParamSpec, TypeVar, get_type_hints
 
-from composify.core import Rule
+from composify.core import Rule, TypeInfo
 from composify.errors import (
     InvalidTypeAnnotation,
     MissingParameterTypeAnnotation,
@@ -75,6 +75,29 @@ def _get_init_func(cls: type):
     return func, func_params
 
 
+def _unwrap_callable(func: Callable) -> Callable:
+    """Returns the function underlying partials and bound methods."""
+    while True:
+        if isinstance(func, partial):
+            func = func.func
+        elif inspect.ismethod(func):
+            func = func.__func__
+        else:
+            return func
+
+
+def _get_func_params(func: Callable) -> list[str]:
+    """Returns the parameters left to be provided. Arguments bound by a partial
+    and the self of a bound method are excluded.
+    """
+    bound = func.keywords if isinstance(func, partial) else {}
+    return [
+        parameter
+        for parameter in inspect.signature(func).parameters
+        if parameter not in bound
+    ]
+
+
 def attach_rule(value: Any, rule: Rule | ConstructRuleSet) -> None:
     """Attach a rule to an object that is collectible via collect_rules().
     To be used with custom rule decorators.
@@ -97,15 +120,22 @@ def _rule_decorator(
     name: str | None = None,
     metadata: Iterable[Qualifier] | None = None,
     return_type: type | None = None,
+    guard: Callable[[list[TypeInfo]], bool] | None = None,
+    tags: Iterable[str] | None = None,
+    enabled: Callable[[], bool] | None = None,
 ) -> Any:
+    if inspect.ismethod(decorated):
+        # Bound methods cannot hold attributes, wrap it to attach the rule.
+        decorated = partial(decorated)
     if inspect.isclass(decorated):
         func, func_params = _get_init_func(decorated)
     else:
         func = decorated
-        func_params = list(inspect.signature(func).parameters)
-    name = name or f"{func.__module__}:{func.__name__}"
+        func_params = _get_func_params(func)
+    unwrapped = _unwrap_callable(func)
+    name = name or f"{unwrapped.__module__}:{unwrapped.__name__}"
     func_id = f"@rule {name}"
-    type_hints = get_type_hints(func, include_extras=True)
+    type_hints = get_type_hints(unwrapped, include_extras=True)
     return_type = return_type or (
         decorated if inspect.isclass(decorated) else type_hints.get("return")
     )
@@ -126,7 +156,9 @@ def _rule_decorator(
         )
         for parameter in func_params
     }
-    effective_name = resolve_type_name(decorated)
+    effective_name = resolve_type_name(
+        decorated if inspect.isclass(decorated) else unwrapped
+    )
 
     rule = Rule(
         decorated,
@@ -134,7 +166,10 @@ def _rule_decorator(
         output_type=return_type_info,
         dependencies=parameter_types,
         priority=priority,
-        is_async=asyncio.iscoroutinefunction(func),
+        is_async=asyncio.iscoroutinefunction(unwrapped),
+        guard=guard,
+        tags=tags,
+        enabled=enabled,
     )
     attach_rule(decorated, rule)
     return decorated
@@ -148,19 +183,25 @@ def rule(
     name: str | None = None,
     metadata: Iterable[Any] | None = None,
     return_type: type | None = None,
+    guard: Callable[[list[TypeInfo]], bool] | None = None,
+    tags: Iterable[str] | None = None,
+    enabled: Callable[[], bool] | None = None,
 ):
     """Marks a function or a class as a rule. Allowing collection via collect_rules().
 
     Args:
         f (RuleFunctionType | None, optional): The function or class to mark as a rule. Defaults to None.
         name (str | None, optional): Override the name of the rule if exists.
-        priority (int, optional): The resolution priority. Higher value equals higher priority. Defaults to 0.
+        priority (int, optional): The resolution priority. Higher value equals higher priority, rules of equal priority are ordered by their canonical name. Defaults to 0.
         metadata (Iterable[Any] | None, optional): Add metadata to all dependencies. Defaults to None.
         return_type (type | None, optional): Override the return type of the rule.
+        guard (Callable[[list[TypeInfo]], bool] | None, optional): Only use the rule when the guard accepts the resolution path. Defaults to None.
+        tags (Iterable[str] | None, optional): Labels to group the rule by, see RuleRegistry.rules_with_tag(). Defaults to None.
+        enabled (Callable[[], bool] | None, optional): Only use the rule while the predicate returns True, checked on every lookup. Unlike qualifiers, it depends on external state rather than metadata. Defaults to None.
         is_optional (bool | None, optional): Override the optionality of the rule.
 
     Returns:
-        The input function or class.
+        The input function or class. Bound methods are returned wrapped in a functools.partial.
 
     Raises:
         MissingReturnTypeAnnotation: Raised if the return type annotation is missing.
@@ -175,6 +216,9 @@ def rule(
             name=name,
             metadata=metadata,
             return_type=return_type,
+            guard=guard,
+            tags=tags,
+            enabled=enabled,
         )
     return _rule_decorator(
         f,
@@ -182,6 +226,9 @@ def rule(
         name=name,
         metadata=metadata,
         return_type=return_type,
+        guard=guard,
+        tags=tags,
+        enabled=enabled,
     )
 
 
@@ -219,6 +266,9 @@ def wraps_rule(wrapped: Any, **kwargs):
                 dependencies=wrapped_rule.dependencies,
                 priority=wrapped_rule.priority,
                 is_async=asyncio.iscoroutinefunction(f),
+                guard=wrapped_rule.guard,
+                tags=wrapped_rule.tags,
+                enabled=wrapped_rule.enabled,
             ),
         )
         return f
@@ -1,4 +1,14 @@
 pyo3::import_exception!(composify.errors, SolveFailureError);
 pyo3::import_exception!(composify.errors, NoSolutionError);
 pyo3::import_exception!(composify.errors, CyclicDependencyError);
+pyo3::import_exception!(composify.errors, MaxDepthExceededError);
 pyo3::import_exception!(composify.errors, NotExclusiveError);
+pyo3::import_exception!(composify.errors, AmbiguousSingleError);
+pyo3::import_exception!(composify.errors, MissingReturnTypeAnnotation);
+pyo3::import_exception!(composify.errors, MissingParameterTypeAnnotation);
+pyo3::import_exception!(composify.errors, TooManyPermutationsError);
+pyo3::import_exception!(composify.errors, TooManySolutionsError);
+pyo3::import_exception!(composify.errors, SolveTimeoutError);
+pyo3::import_exception!(composify.errors, AsyncSolutionError);
+pyo3::import_exception!(composify.errors, DuplicateRuleError);
+pyo3::import_exception!(composify.errors, AsyncQualifierError);
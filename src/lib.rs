@@ -14,21 +14,42 @@ mod type_info;
 #[pyo3(name = "core")]
 fn core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     let py = m.py();
+    m.add_class::<type_info::NamingScheme>()?;
     m.add_class::<type_info::TypeInfo>()?;
     m.add_class::<rules::Dependency>()?;
     m.add_class::<rules::DependenciesIter>()?;
     m.add_class::<rules::Dependencies>()?;
     m.add_class::<rules::Rule>()?;
+    m.add_class::<registry::RuleRegistryIter>()?;
+    m.add_class::<registry::OnDuplicate>()?;
     m.add_class::<registry::RuleRegistry>()?;
+    m.add_class::<metadata::MetadataSetIter>()?;
     m.add_class::<metadata::MetadataSet>()?;
+    m.add_class::<metadata::Not>()?;
+    m.add_class::<metadata::QualifierMode>()?;
+    m.add_class::<metadata::Qualifiers>()?;
     m.add_class::<solutions::SolutionArg>()?;
     m.add_class::<solutions::SolutionArgsCollection>()?;
     m.add_class::<solutions::Solution>()?;
     m.add_class::<solve_parameters::SolveCardinality>()?;
     m.add_class::<solve_parameters::SolveSpecificity>()?;
+    m.add_class::<solve_parameters::First>()?;
+    m.add_class::<solve_parameters::Strict>()?;
     m.add_class::<solve_parameters::SolveParameter>()?;
+    m.add_class::<solve_parameters::DependencyMarker>()?;
     m.add("SolvingError", py.get_type::<solver::SolvingError>())?;
+    m.add_class::<solver::MockValue>()?;
+    m.add_class::<solver::CollectValues>()?;
+    m.add_class::<solver::Stats>()?;
+    m.add_class::<solver::RuleAttempt>()?;
+    m.add_class::<solver::Explanation>()?;
+    m.add_class::<solver::DependencyGraph>()?;
+    m.add_class::<solver::ResolutionOrder>()?;
+    m.add_class::<solver::SolverOverrides>()?;
+    m.add_class::<solver::AsyncResolution>()?;
     m.add_class::<solver::Solver>()?;
+    m.add_function(wrap_pyfunction!(type_info::set_canonical_name_strategy, m)?)?;
+    m.add_function(wrap_pyfunction!(rules::make_rule, m)?)?;
 
     Ok(())
 }
@@ -1,63 +1,239 @@
-use pyo3::exceptions::PyKeyError;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyKeyError, PyTypeError};
 use pyo3::types::{PyBool, PyTuple, PyType};
 use pyo3::PyObject;
 use pyo3::{intern, prelude::*};
-use std::collections::hash_map::Values;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{Display, Write};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::slice::Iter;
 use std::sync::Arc;
 
-#[pyclass(frozen, eq, hash, subclass, module = "composify.core.metadata")]
+use crate::errors;
+use crate::type_info::TypeInfo;
+
+#[pyclass(frozen, subclass, module = "composify.core.metadata")]
 #[derive(Debug, Default, Clone)]
 pub struct MetadataSet {
-    map: Arc<HashMap<isize, PyObject>>,
+    /// Shared between clones, the set is immutable once built.
+    map: Arc<HashMap<isize, Vec<PyObject>>>,
+    /// The type hash and hash of every item, sorted.
+    item_hashes: Arc<Vec<(isize, isize)>>,
+    len: usize,
     hash: u64,
 }
 
 impl MetadataSet {
+    /// Multiple metadata of the same type are all kept.
     pub fn new(items: Vec<Bound<'_, PyAny>>) -> PyResult<MetadataSet> {
+        Self::build(items.into_iter().enumerate().collect(), false)
+    }
+
+    /// Only the last metadata of each type is kept.
+    pub fn new_single_valued(items: Vec<Bound<'_, PyAny>>) -> PyResult<MetadataSet> {
+        Self::build(items.into_iter().enumerate().collect(), true)
+    }
+
+    /// Like `new`, the items are paired with their index in the annotation for error messages.
+    pub fn from_indexed(items: Vec<(usize, Bound<'_, PyAny>)>) -> PyResult<MetadataSet> {
+        Self::build(items, false)
+    }
+
+    fn build(items: Vec<(usize, Bound<'_, PyAny>)>, single_valued: bool) -> PyResult<MetadataSet> {
+        let mut map: HashMap<isize, Vec<(usize, Bound<'_, PyAny>)>> = HashMap::new();
+        for (index, item) in items {
+            let key = hash_metadata_type(&item, index)?;
+            let values = map.entry(key).or_default();
+            if single_valued {
+                values.clear();
+            }
+            values.push((index, item));
+        }
+        let mut item_hashes = Vec::new();
+        for (key, values) in map.iter() {
+            for (index, item) in values {
+                item_hashes.push((*key, hash_metadata(item, *index)?));
+            }
+        }
+        // Sorted so that the hash does not depend on the order of the items.
+        item_hashes.sort_unstable_by_key(|(key, item_hash)| (*item_hash, *key));
         let mut hasher = DefaultHasher::default();
-        let mut map = HashMap::new();
-        for item in items {
-            hasher.write_isize(item.hash()?);
-            let key = item.get_type().hash()?;
-            map.insert(key, item.unbind());
+        for (_, item_hash) in &item_hashes {
+            hasher.write_isize(*item_hash);
         }
+        let len = item_hashes.len();
+        // Empty sets hash like the default set, so parsed and default types compare equal.
+        let hash = if len == 0 { 0 } else { hasher.finish() };
         Ok(MetadataSet {
-            map: Arc::new(map),
-            hash: hasher.finish(),
+            map: Arc::new(
+                map.into_iter()
+                    .map(|(k, v)| (k, v.into_iter().map(|(_, item)| item.unbind()).collect()))
+                    .collect(),
+            ),
+            item_hashes: Arc::new(item_hashes),
+            len,
+            hash,
         })
     }
 }
 
+/// Hash a metadata item, naming it and its index if it is unhashable.
+fn hash_metadata(item: &Bound<'_, PyAny>, index: usize) -> PyResult<isize> {
+    item.hash().map_err(|cause| {
+        let error = PyTypeError::new_err(format!(
+            "Metadata {} at index {index} is not hashable: {cause}",
+            repr_or_type(item)
+        ));
+        error.set_cause(item.py(), Some(cause));
+        error
+    })
+}
+
+/// Hash the type of a metadata item, naming the item and its index if the type is unhashable.
+fn hash_metadata_type(item: &Bound<'_, PyAny>, index: usize) -> PyResult<isize> {
+    item.get_type().hash().map_err(|cause| {
+        let error = PyTypeError::new_err(format!(
+            "Type of metadata {} at index {index} is not hashable: {cause}",
+            repr_or_type(item)
+        ));
+        error.set_cause(item.py(), Some(cause));
+        error
+    })
+}
+
+/// The repr of the item, or its type name if the repr fails as well.
+fn repr_or_type(item: &Bound<'_, PyAny>) -> String {
+    item.repr()
+        .map(|repr| repr.to_string())
+        .unwrap_or_else(|_| format!("<{}>", item.get_type()))
+}
+
 impl Hash for MetadataSet {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.hash.hash(state);
     }
 }
 
+/// Sets are equal if they are the same set, or hold equal values of the same types. The hashes
+/// reject most unequal sets without the GIL. Values whose comparison raises are only equal to
+/// themselves here, `MetadataSet.__eq__` raises instead.
 impl PartialEq for MetadataSet {
     fn eq(&self, other: &Self) -> bool {
-        self.hash == other.hash
+        Arc::ptr_eq(&self.map, &other.map)
+            || (self.hash == other.hash
+                && self.item_hashes == other.item_hashes
+                && Python::with_gil(|py| {
+                    self.is_subset_by(py, other, |value, other| {
+                        Ok(value.is(other) || value.eq(other).unwrap_or(false))
+                    })
+                    .unwrap_or(false)
+                }))
     }
 }
 
+impl Eq for MetadataSet {}
+
+/// Values present in and absent from another metadata.
+type Partition<'py> = (Vec<Bound<'py, PyAny>>, Vec<Bound<'py, PyAny>>);
+
 impl MetadataSet {
-    pub fn iter(&self) -> Values<isize, PyObject> {
-        self.map.values()
+    pub fn iter(&self) -> impl Iterator<Item = &PyObject> {
+        self.map.values().flatten()
+    }
+
+    /// If every value has a distinct equal value of the same type in the other metadata.
+    fn is_subset_by(
+        &self,
+        py: Python,
+        metadata: &MetadataSet,
+        eq: impl Fn(&Bound<'_, PyAny>, &Bound<'_, PyAny>) -> PyResult<bool>,
+    ) -> PyResult<bool> {
+        for (key, values) in self.map.iter() {
+            let Some(others) = metadata.map.get(key) else {
+                return Ok(false);
+            };
+            if values.len() > others.len() {
+                return Ok(false);
+            }
+            let mut matched = vec![false; others.len()];
+            'value: for value in values {
+                for (index, other) in others.iter().enumerate() {
+                    if !matched[index] && eq(value.bind(py), other.bind(py))? {
+                        matched[index] = true;
+                        continue 'value;
+                    }
+                }
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// If the other metadata contains a value equal to the given value.
+    fn contains_value(&self, py: Python, key: &isize, value: &PyObject) -> PyResult<bool> {
+        if let Some(others) = self.map.get(key) {
+            for other in others {
+                if value.bind(py).eq(other.bind(py))? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Split the metadata of this set by whether they are present in the other metadata.
+    /// Each value in the other metadata matches at most one value of this set.
+    fn partition<'py>(&self, py: Python<'py>, metadata: &MetadataSet) -> PyResult<Partition<'py>> {
+        let mut present = Vec::new();
+        let mut absent = Vec::new();
+        for (key, values) in self.map.iter() {
+            let others = metadata.map.get(key).map_or(&[][..], Vec::as_slice);
+            let mut matched = vec![false; others.len()];
+            'value: for value in values {
+                let value = value.bind(py).clone();
+                for (index, other) in others.iter().enumerate() {
+                    if !matched[index] && value.eq(other.bind(py))? {
+                        matched[index] = true;
+                        present.push(value);
+                        continue 'value;
+                    }
+                }
+                absent.push(value);
+            }
+        }
+        Ok((present, absent))
+    }
+
+    /// Count the metadata of this set that are present in the other metadata.
+    pub fn count_matching(&self, py: Python, metadata: &MetadataSet) -> PyResult<usize> {
+        let mut count = 0;
+        for (key, values) in self.map.iter() {
+            for value in values {
+                if metadata.contains_value(py, key, value)? {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
     }
 }
 
 #[pymethods]
 impl MetadataSet {
+    #[new]
+    #[pyo3(signature = (items, single_valued=false))]
+    pub fn __new__(items: Vec<Bound<'_, PyAny>>, single_valued: bool) -> PyResult<MetadataSet> {
+        Self::build(items.into_iter().enumerate().collect(), single_valued)
+    }
+
+    /// Get the first metadata of the type.
     pub fn get<'py>(
         slf: PyRef<'py, Self>,
         type_info: Bound<'py, PyType>,
     ) -> PyResult<Option<Bound<'py, PyAny>>> {
         let key = type_info.hash()?;
-        if let Some(o) = slf.map.get(&key) {
+        if let Some(o) = slf.map.get(&key).and_then(|v| v.first()) {
             let py = slf.py();
             Ok(Some(o.clone_ref(py).into_bound(py)))
         } else {
@@ -65,12 +241,29 @@ impl MetadataSet {
         }
     }
 
+    /// Get all metadata of the type.
+    pub fn get_all<'py>(
+        slf: PyRef<'py, Self>,
+        type_info: Bound<'py, PyType>,
+    ) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        let key = type_info.hash()?;
+        let py = slf.py();
+        Ok(match slf.map.get(&key) {
+            Some(values) => values
+                .iter()
+                .map(|o| o.clone_ref(py).into_bound(py))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    /// Get the first metadata of the type.
     pub fn __getitem__<'py>(
         slf: PyRef<'py, Self>,
         type_info: Bound<'py, PyType>,
     ) -> PyResult<Bound<'py, PyAny>> {
         let key = type_info.hash()?;
-        if let Some(o) = slf.map.get(&key) {
+        if let Some(o) = slf.map.get(&key).and_then(|v| v.first()) {
             let py = slf.py();
             Ok(o.clone_ref(py).into_bound(py))
         } else {
@@ -81,18 +274,83 @@ impl MetadataSet {
         }
     }
 
+    pub fn __iter__(&self, py: Python) -> PyResult<Py<MetadataSetIter>> {
+        let iter = MetadataSetIter {
+            inner: self
+                .iter()
+                .map(|o| o.clone_ref(py))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        };
+        Py::new(py, iter)
+    }
+
     pub fn __repr__(&self) -> PyResult<String> {
         Ok(self.to_string())
     }
 
-    /// If this metadata is subset of the other metadata
-    pub fn issubset(&self, metadata: &MetadataSet) -> bool {
-        self.map.keys().all(|k| metadata.map.contains_key(k))
+    /// If metadata of the type is stored, objects other than types are never contained.
+    pub fn __contains__(&self, type_info: Bound<'_, PyAny>) -> PyResult<bool> {
+        let Ok(type_info) = type_info.downcast::<PyType>() else {
+            return Ok(false);
+        };
+        Ok(self.map.contains_key(&type_info.hash()?))
+    }
+
+    /// The number of stored metadata, counting each of the same type.
+    pub fn __len__(&self) -> usize {
+        self.len
+    }
+
+    pub fn __hash__(&self) -> u64 {
+        self.hash
+    }
+
+    /// Equal if both hold equal values, raising if comparing the values raises.
+    pub fn __eq__(&self, py: Python, other: PyRef<'_, Self>) -> PyResult<bool> {
+        Ok(Arc::ptr_eq(&self.map, &other.map)
+            || (self.hash == other.hash && self.len == other.len && self.issubset(py, &other)?))
+    }
+
+    /// If this metadata is subset of the other metadata, comparing the stored values.
+    /// Repeated values must be repeated at least as many times in the other metadata.
+    pub fn issubset(&self, py: Python, metadata: &MetadataSet) -> PyResult<bool> {
+        self.is_subset_by(py, metadata, |value, other| value.eq(other))
+    }
+
+    /// If this metadata is superset of the other metadata, comparing the stored values.
+    pub fn issuperset(&self, py: Python, metadata: &MetadataSet) -> PyResult<bool> {
+        metadata.issubset(py, self)
+    }
+
+    /// Metadata of either set, values repeated in both are kept as often as the larger count.
+    pub fn union(&self, py: Python, metadata: &MetadataSet) -> PyResult<MetadataSet> {
+        let (_, extra) = metadata.partition(py, self)?;
+        let mut items: Vec<Bound<'_, PyAny>> = self.iter().map(|o| o.bind(py).clone()).collect();
+        items.extend(extra);
+        Self::new(items)
     }
 
-    /// If this metadata is superset of the other metadata
-    pub fn issuperset(&self, metadata: &MetadataSet) -> bool {
-        metadata.issubset(self)
+    /// Metadata present in both sets.
+    pub fn intersection(&self, py: Python, metadata: &MetadataSet) -> PyResult<MetadataSet> {
+        Self::new(self.partition(py, metadata)?.0)
+    }
+
+    /// Metadata of this set that are not present in the other metadata.
+    pub fn difference(&self, py: Python, metadata: &MetadataSet) -> PyResult<MetadataSet> {
+        Self::new(self.partition(py, metadata)?.1)
+    }
+
+    /// Metadata of this set for which the predicate returns a truthy value.
+    pub fn filter(&self, py: Python, predicate: Bound<'_, PyAny>) -> PyResult<MetadataSet> {
+        let mut items = Vec::new();
+        for value in self.iter() {
+            let value = value.bind(py);
+            if predicate.call1((value,))?.is_truthy()? {
+                items.push(value.clone());
+            }
+        }
+        Self::new(items)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -100,22 +358,28 @@ impl MetadataSet {
     }
 }
 
-impl Display for MetadataSet {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_char('(')?;
+#[pyclass(module = "composify.core.metadata")]
+pub struct MetadataSetIter {
+    inner: std::vec::IntoIter<PyObject>,
+}
 
-        let mut first = true;
+#[pymethods]
+impl MetadataSetIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
 
-        for item in self.map.values() {
-            if !first {
-                f.write_str(", ")?;
-            }
-            item.fmt(f)?;
-            first = false;
-        }
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<PyObject> {
+        slf.inner.next()
+    }
+}
 
-        f.write_char(')')?;
-        Ok(())
+impl Display for MetadataSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Sorted since the items are stored in hash order, which differs between runs.
+        let mut items: Vec<String> = self.iter().map(|item| item.to_string()).collect();
+        items.sort_unstable();
+        write!(f, "({})", items.join(", "))
     }
 }
 
@@ -123,98 +387,494 @@ impl Display for MetadataSet {
 pub struct Qualifier {
     inner: Arc<PyObject>,
     inner_self: Option<Arc<PyObject>>,
+    /// Invert the result of the inner qualifier.
+    negated: bool,
+    /// The inner qualifier also takes the requested type, see `QUALIFY_FOR_METHOD_NAME`.
+    contextual: bool,
 }
 
 pub const QUALIFY_METHOD_NAME: &str = "qualify";
+/// Preferred over `qualify` when present, called with the attributes and the requested type.
+pub const QUALIFY_FOR_METHOD_NAME: &str = "qualify_for";
+/// Optional, called with the rejected attributes to describe the rejection in diagnostics.
+pub const REJECTION_REASON_METHOD_NAME: &str = "rejection_reason";
 
 impl Qualifier {
     pub fn new(qualifier: Bound<PyAny>) -> Self {
-        if let Ok(func) = qualifier.getattr(intern!(qualifier.py(), QUALIFY_METHOD_NAME)) {
+        let py = qualifier.py();
+        // Composite qualifiers only need the requested type if one of their parts does.
+        let contextual = if let Ok(not) = qualifier.downcast::<Not>() {
+            not.get().qualifier.contextual
+        } else if let Ok(qualifiers) = qualifier.downcast::<Qualifiers>() {
+            qualifiers.get().is_contextual()
+        } else {
+            qualifier
+                .hasattr(intern!(py, QUALIFY_FOR_METHOD_NAME))
+                .unwrap_or(false)
+        };
+        let method = if contextual {
+            intern!(py, QUALIFY_FOR_METHOD_NAME)
+        } else {
+            intern!(py, QUALIFY_METHOD_NAME)
+        };
+        if let Ok(func) = qualifier.getattr(method) {
             Self {
                 inner: Arc::new(func.unbind()),
                 inner_self: Some(Arc::new(qualifier.unbind())),
+                negated: false,
+                contextual,
             }
         } else {
             Self {
                 inner: Arc::new(qualifier.unbind()),
                 inner_self: None,
+                negated: false,
+                contextual: false,
             }
         }
     }
 
+    /// If the object implements either qualifier protocol method.
+    pub fn is_qualifier(object: &Bound<PyAny>) -> PyResult<bool> {
+        let py = object.py();
+        Ok(object.hasattr(intern!(py, QUALIFY_FOR_METHOD_NAME))?
+            || object.hasattr(intern!(py, QUALIFY_METHOD_NAME))?)
+    }
+
+    /// The python object the qualifier was created from.
+    fn source(&self) -> &PyObject {
+        self.inner_self.as_deref().unwrap_or(&self.inner)
+    }
+
+    /// Qualifies exactly when the given qualifier does not.
+    pub fn negated(qualifier: Bound<PyAny>) -> Self {
+        let mut qualifier = Self::new(qualifier);
+        qualifier.negated = !qualifier.negated;
+        qualifier
+    }
+
     /// Invoke the inner python qualifier object.
-    /// Takes a reference to a bound python tuple as args.
-    pub fn call(&self, args: &Bound<PyTuple>) -> PyResult<bool> {
-        let q = self.inner.bind(args.py()).call1(args)?;
-        let q = q.downcast_into::<PyBool>()?;
-        Ok(q.is_true())
+    /// Takes a reference to a bound python tuple as args. Returns the result before negation,
+    /// a bool or an awaitable resolving to one.
+    pub fn call<'py>(&self, args: &Bound<'py, PyTuple>) -> PyResult<Bound<'py, PyAny>> {
+        self.inner.bind(args.py()).call1(args)
     }
 
-    /// Invoke the inner python qualifier for an attribute set.
-    pub fn qualify(&self, py: Python<'_>, attrs: &MetadataSet) -> PyResult<bool> {
-        let args = PyTuple::new(py, [attrs.clone()])?;
-        self.call(&args)
+    /// Invoke the inner python qualifier for an attribute set. Contextual qualifiers also
+    /// receive the requested type, None when qualifying outside of a solve.
+    pub fn qualify(
+        &self,
+        py: Python<'_>,
+        attrs: &MetadataSet,
+        requested: Option<&TypeInfo>,
+    ) -> PyResult<bool> {
+        let result = if self.contextual {
+            self.call(&(attrs.clone(), requested.cloned()).into_pyobject(py)?)?
+        } else {
+            self.call(&PyTuple::new(py, [attrs.clone()])?)?
+        };
+        let qualified = match result.downcast::<PyBool>() {
+            Ok(result) => result.is_true(),
+            Err(e) if !result.hasattr(intern!(py, "__await__"))? => return Err(e.into()),
+            Err(_) => {
+                let key = AwaitedKey {
+                    source: self.source().as_ptr() as usize,
+                    attrs: attrs.clone(),
+                    requested: requested.filter(|_| self.contextual).cloned(),
+                };
+                self.awaited(result, key)?
+            }
+        };
+        Ok(qualified != self.negated)
+    }
+
+    /// The awaited result of an async qualifier. If it is yet to be awaited, the awaitable is
+    /// handed to the resolution in progress, which resumes once it has the result.
+    fn awaited(&self, awaitable: Bound<PyAny>, key: AwaitedKey) -> PyResult<bool> {
+        let outcome = AWAITED_QUALIFIERS.with_borrow_mut(|awaited| {
+            let awaited = awaited.as_mut()?;
+            match awaited.results.get(&key) {
+                Some(qualified) => Some(Ok(*qualified)),
+                None => {
+                    awaited.pending = Some((key, awaitable.clone().unbind()));
+                    Some(Err(AwaitingQualifier::new_err(format!(
+                        "Awaiting qualifier {self}"
+                    ))))
+                }
+            }
+        });
+        match outcome {
+            Some(Err(e)) => Err(e),
+            Some(Ok(qualified)) => {
+                close_awaitable(&awaitable)?;
+                Ok(qualified)
+            }
+            None => {
+                close_awaitable(&awaitable)?;
+                Err(errors::AsyncQualifierError::new_err(format!(
+                    "Qualifier {self} is async, it can only be used by resolve_async"
+                )))
+            }
+        }
+    }
+}
+
+create_exception!(composify.core.metadata, AwaitingQualifier, PyException);
+
+/// Identifies an evaluation of an async qualifier, by the qualifier object, the attributes
+/// and for contextual qualifiers the requested type. The qualifier object is compared by
+/// identity, it is kept alive by the target or the registry for the whole resolution.
+#[derive(PartialEq, Eq, Hash)]
+pub struct AwaitedKey {
+    source: usize,
+    attrs: MetadataSet,
+    requested: Option<TypeInfo>,
+}
+
+/// Results of async qualifiers awaited by a resolution, and the awaitable it stopped at.
+#[derive(Default)]
+pub struct AwaitedQualifiers {
+    results: HashMap<AwaitedKey, bool>,
+    pending: Option<(AwaitedKey, PyObject)>,
+}
+
+impl AwaitedQualifiers {
+    /// The awaitable the last evaluation stopped at, if any.
+    pub fn pending(&self, py: Python) -> Option<PyObject> {
+        self.pending
+            .as_ref()
+            .map(|(_, awaitable)| awaitable.clone_ref(py))
+    }
+
+    /// Record the result of the pending awaitable.
+    pub fn resolve(&mut self, qualified: bool) {
+        if let Some((key, _)) = self.pending.take() {
+            self.results.insert(key, qualified);
+        }
+    }
+
+    /// Evaluate with async qualifiers answered from the awaited results, any other async
+    /// qualifier is recorded as pending and stops the evaluation with an error.
+    pub fn evaluate<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.pending = None;
+        let previous = AWAITED_QUALIFIERS.replace(Some(std::mem::take(self)));
+        let result = f();
+        *self = AWAITED_QUALIFIERS
+            .replace(previous)
+            .expect("awaited qualifiers are set");
+        result
+    }
+}
+
+thread_local! {
+    /// The awaited qualifiers of the resolution in progress on this thread.
+    static AWAITED_QUALIFIERS: RefCell<Option<AwaitedQualifiers>> = const { RefCell::new(None) };
+}
+
+/// Close a coroutine whose result is not needed, so it is not reported as never awaited.
+fn close_awaitable(awaitable: &Bound<PyAny>) -> PyResult<()> {
+    let py = awaitable.py();
+    if awaitable.hasattr(intern!(py, "close"))? {
+        awaitable.call_method0(intern!(py, "close"))?;
+    }
+    Ok(())
+}
+
+impl Qualifier {
+    /// Describe why the attributes do not qualify, None if they do. Nested qualifiers describe
+    /// the part rejecting the attributes, others use their `rejection_reason` if they have one
+    /// and otherwise the qualifier itself.
+    fn rejection(
+        &self,
+        py: Python,
+        attrs: &MetadataSet,
+        requested: Option<&TypeInfo>,
+    ) -> PyResult<Option<String>> {
+        let source = self.source().bind(py);
+        let explained = !self.negated && self.inner_self.is_some();
+        if explained {
+            if let Ok(qualifiers) = source.downcast::<Qualifiers>() {
+                return qualifiers.get().rejection(py, attrs, requested);
+            }
+        }
+        if self.qualify(py, attrs, requested)? {
+            return Ok(None);
+        }
+        if explained && source.hasattr(intern!(py, REJECTION_REASON_METHOD_NAME))? {
+            return source
+                .call_method1(intern!(py, REJECTION_REASON_METHOD_NAME), (attrs.clone(),))?
+                .extract()
+                .map(Some);
+        }
+        Ok(Some(format!("qualifier {self}")))
     }
 }
 
 impl Display for Qualifier {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Some(inner_self) = &self.inner_self {
-            inner_self.fmt(f)
-        } else {
-            self.inner.fmt(f)
+        if self.negated {
+            f.write_char('!')?;
         }
+        self.source().fmt(f)
     }
 }
 
+/// Qualifier passing when the wrapped qualifier fails.
+#[pyclass(frozen, module = "composify.core.metadata")]
+pub struct Not {
+    qualifier: Qualifier,
+    hash: isize,
+}
+
+#[pymethods]
+impl Not {
+    #[new]
+    pub fn __new__(qualifier: Bound<PyAny>) -> PyResult<Self> {
+        let hash = qualifier.hash()?;
+        Ok(Self {
+            qualifier: Qualifier::negated(qualifier),
+            hash: !hash,
+        })
+    }
+
+    pub fn qualify(&self, py: Python, attributes: &MetadataSet) -> PyResult<bool> {
+        self.qualifier.qualify(py, attributes, None)
+    }
+
+    #[pyo3(signature = (attributes, requested=None))]
+    pub fn qualify_for(
+        &self,
+        py: Python,
+        attributes: &MetadataSet,
+        requested: Option<TypeInfo>,
+    ) -> PyResult<bool> {
+        self.qualifier.qualify(py, attributes, requested.as_ref())
+    }
+
+    pub fn __hash__(&self) -> isize {
+        self.hash
+    }
+
+    pub fn __eq__(&self, other: &Bound<PyAny>) -> PyResult<bool> {
+        let py = other.py();
+        match other.downcast::<Not>() {
+            Ok(other) => self
+                .qualifier
+                .source()
+                .bind(py)
+                .eq(other.get().qualifier.source()),
+            Err(_) => Ok(false),
+        }
+    }
+
+    pub fn __repr__(&self) -> String {
+        self.qualifier.to_string()
+    }
+}
+
+/// How the results of multiple qualifiers are combined.
+#[pyclass(hash, eq, eq_int, frozen, module = "composify.core.metadata")]
+#[derive(PartialEq, Clone, Debug, Hash, Default)]
+pub enum QualifierMode {
+    /// Qualified if all qualifiers pass.
+    #[default]
+    All,
+    /// Qualified if any qualifier passes.
+    Any,
+}
+
 #[pyclass(frozen, eq, module = "composify.core.metadata")]
 #[derive(Debug, Default, Clone)]
 pub struct Qualifiers {
-    qualifiers: Vec<Qualifier>,
+    /// Shared between clones like the metadata of `MetadataSet`.
+    qualifiers: Arc<[Qualifier]>,
+    mode: QualifierMode,
     hash: u64,
 }
 
 #[pymethods]
 impl Qualifiers {
     #[new]
-    pub fn __new__(items: Vec<Bound<PyAny>>) -> PyResult<Self> {
+    #[pyo3(signature = (items, mode=QualifierMode::All))]
+    pub fn __new__(items: Vec<Bound<PyAny>>, mode: QualifierMode) -> PyResult<Self> {
+        Self::from_indexed(items.into_iter().enumerate().collect(), mode)
+    }
+
+    /// Qualified if all of the qualifiers pass.
+    #[staticmethod]
+    pub fn all(items: Vec<Bound<PyAny>>) -> PyResult<Self> {
+        Self::__new__(items, QualifierMode::All)
+    }
+
+    /// Qualified if any of the qualifiers passes.
+    #[staticmethod]
+    pub fn any(items: Vec<Bound<PyAny>>) -> PyResult<Self> {
+        Self::__new__(items, QualifierMode::Any)
+    }
+
+    #[getter]
+    pub fn mode(&self) -> QualifierMode {
+        self.mode.clone()
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(self.to_string())
+    }
+
+    pub fn __hash__(&self) -> PyResult<u64> {
+        Ok(self.hash)
+    }
+
+    /// Qualified if both qualify, the qualifiers of `All` operands are concatenated.
+    pub fn __and__(slf: &Bound<'_, Self>, other: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        Self::combine(slf, other, QualifierMode::All)
+    }
+
+    /// Qualified if either qualifies, the qualifiers of `Any` operands are concatenated.
+    pub fn __or__(slf: &Bound<'_, Self>, other: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        Self::combine(slf, other, QualifierMode::Any)
+    }
+
+    /// Empty qualifiers always qualify regardless of the mode. The requested type is passed to
+    /// the qualifiers implementing `qualify_for`.
+    #[pyo3(signature = (attrs, requested=None))]
+    pub fn qualify(
+        &self,
+        py: Python,
+        attrs: &MetadataSet,
+        requested: Option<TypeInfo>,
+    ) -> PyResult<bool> {
+        self.qualify_requested(py, attrs, requested.as_ref())
+    }
+
+    /// The contextual qualifier protocol, lets nested qualifiers receive the requested type.
+    #[pyo3(signature = (attrs, requested=None))]
+    pub fn qualify_for(
+        &self,
+        py: Python,
+        attrs: &MetadataSet,
+        requested: Option<TypeInfo>,
+    ) -> PyResult<bool> {
+        self.qualify_requested(py, attrs, requested.as_ref())
+    }
+}
+
+impl Qualifiers {
+    /// Like `__new__`, the items are paired with their index in the annotation for error
+    /// messages.
+    pub fn from_indexed(items: Vec<(usize, Bound<PyAny>)>, mode: QualifierMode) -> PyResult<Self> {
         let mut hasher = DefaultHasher::default();
+        mode.hash(&mut hasher);
         let mut qualifiers = Vec::new();
-        for p in items {
-            hasher.write_isize(p.hash()?);
+        for (index, p) in items {
+            hasher.write_isize(hash_metadata(&p, index)?);
             qualifiers.push(Qualifier::new(p));
         }
+        // Empty qualifiers always qualify whatever the mode, like the default qualifiers.
+        let hash = if qualifiers.is_empty() {
+            0
+        } else {
+            hasher.finish()
+        };
         Ok(Self {
-            qualifiers,
-            hash: hasher.finish(),
+            qualifiers: qualifiers.into(),
+            mode,
+            hash,
         })
     }
 
-    pub fn __repr__(&self) -> PyResult<String> {
-        Ok(self.to_string())
+    pub fn is_empty(&self) -> bool {
+        self.qualifiers.is_empty()
     }
 
-    pub fn __hash__(&self) -> PyResult<u64> {
-        Ok(self.hash)
+    /// Combine the operands under the mode. Operands already combined under the mode, or
+    /// with a single qualifier, contribute their qualifiers, others are nested whole. The
+    /// hash is computed over the resulting qualifiers like any other set.
+    fn combine(
+        slf: &Bound<'_, Self>,
+        other: &Bound<'_, PyAny>,
+        mode: QualifierMode,
+    ) -> PyResult<PyObject> {
+        let py = slf.py();
+        if !Qualifier::is_qualifier(other)? {
+            return Ok(py.NotImplemented());
+        }
+        let mut items = Vec::new();
+        for operand in [slf.as_any(), other] {
+            let Ok(qualifiers) = operand.downcast::<Qualifiers>() else {
+                items.push(operand.clone());
+                continue;
+            };
+            let qualifiers = qualifiers.get();
+            if qualifiers.is_empty() && mode == QualifierMode::Any {
+                // Empty qualifiers always qualify, and so does the disjunction.
+                return Ok(Qualifiers::default().into_pyobject(py)?.into_any().unbind());
+            }
+            if qualifiers.mode == mode || qualifiers.qualifiers.len() <= 1 {
+                items.extend(qualifiers.iter().map(|q| q.source().bind(py).clone()));
+            } else {
+                items.push(operand.clone());
+            }
+        }
+        let combined = Self::from_indexed(items.into_iter().enumerate().collect(), mode)?;
+        Ok(combined.into_pyobject(py)?.into_any().unbind())
+    }
+
+    /// If any qualifier depends on the requested type.
+    pub fn is_contextual(&self) -> bool {
+        self.qualifiers.iter().any(|q| q.contextual)
     }
 
-    pub fn qualify(&self, py: Python, attrs: &MetadataSet) -> PyResult<bool> {
-        let args = PyTuple::new(py, [attrs.clone()])?;
+    pub fn qualify_requested(
+        &self,
+        py: Python,
+        attrs: &MetadataSet,
+        requested: Option<&TypeInfo>,
+    ) -> PyResult<bool> {
+        if self.qualifiers.is_empty() {
+            return Ok(true);
+        }
+        let any = self.mode == QualifierMode::Any;
         for q in self.qualifiers.iter() {
-            if !q.call(&args)? {
-                return Ok(false);
+            if q.qualify(py, attrs, requested)? == any {
+                return Ok(any);
             }
         }
-        Ok(true)
+        Ok(!any)
     }
-}
 
-impl Qualifiers {
-    pub fn is_empty(&self) -> bool {
-        self.qualifiers.is_empty()
+    /// Describe why the attributes do not qualify, None if they do. Evaluates each qualifier at
+    /// most once like `qualify_requested`, so lookups learn the reason in the same pass.
+    pub fn rejection(
+        &self,
+        py: Python,
+        attrs: &MetadataSet,
+        requested: Option<&TypeInfo>,
+    ) -> PyResult<Option<String>> {
+        if self.qualifiers.is_empty() {
+            return Ok(None);
+        }
+        for q in self.qualifiers.iter() {
+            match self.mode {
+                QualifierMode::All => {
+                    if let Some(rejection) = q.rejection(py, attrs, requested)? {
+                        return Ok(Some(rejection));
+                    }
+                }
+                QualifierMode::Any => {
+                    if q.qualify(py, attrs, requested)? {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+        Ok(match self.mode {
+            QualifierMode::All => None,
+            QualifierMode::Any => Some(format!("qualifiers {self}")),
+        })
     }
 
-    pub fn iter(&self) -> Iter<Qualifier> {
+    pub fn iter(&self) -> Iter<'_, Qualifier> {
         self.qualifiers.iter()
     }
 }
@@ -223,11 +883,15 @@ impl Display for Qualifiers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_char('(')?;
 
+        let separator = match self.mode {
+            QualifierMode::All => ", ",
+            QualifierMode::Any => " | ",
+        };
         let mut first = true;
 
         for qualifier in self.qualifiers.iter() {
             if !first {
-                f.write_str(", ")?;
+                f.write_str(separator)?;
             }
             qualifier.fmt(f)?;
             first = false;
@@ -1,13 +1,18 @@
 use pyo3::{
+    intern,
     prelude::*,
+    sync::MutexExt,
     types::{PyTuple, PyType},
 };
+use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use crate::{
+    errors,
     metadata::{MetadataSet, Qualifiers},
-    rules::Rule,
-    solve_parameters::SolveSpecificity,
+    rules::{Dependency, Rule},
+    solve_parameters::{SolveCardinality, SolveSpecificity},
     type_info::TypeInfo,
 };
 
@@ -17,6 +22,8 @@ pub type TypeHash = isize;
 pub struct TypeRegistry {
     subclasses: HashMap<TypeHash, HashSet<TypeHash>>,
     superclasses: HashMap<TypeHash, Vec<TypeHash>>,
+    /// The registered types, kept to recompute their MRO in `reindex`.
+    types: HashMap<TypeHash, Arc<Py<PyType>>>,
 }
 
 /// Includes self type and builtin types.
@@ -41,18 +48,83 @@ impl TypeRegistry {
         }
     }
 
+    /// The MRO hashes of a type whose only base is already registered, which is the type
+    /// followed by the MRO of the base. Other types must be resolved through `mro()`.
+    fn derive_mro(
+        &self,
+        typing: &Bound<PyType>,
+        type_hash: TypeHash,
+    ) -> PyResult<Option<Vec<TypeHash>>> {
+        let py = typing.py();
+        // A metaclass could override `mro()`, only the default linearization is derived.
+        if !typing.get_type().is(py.get_type::<PyType>()) {
+            return Ok(None);
+        }
+        let bases = typing
+            .getattr(intern!(py, "__bases__"))?
+            .downcast_into::<PyTuple>()?;
+        if bases.len() != 1 {
+            return Ok(None);
+        }
+        let base_hash = bases.get_item(0)?.hash()?;
+        Ok(self.superclasses.get(&base_hash).map(|base_mro| {
+            std::iter::once(type_hash)
+                .chain(base_mro.iter().copied())
+                .collect()
+        }))
+    }
+
     pub fn add(&mut self, typing: &Bound<PyType>) -> PyResult<()> {
         let type_hash = typing.hash()?;
-        let mut superclass_hashes = Vec::new();
-        for s in resolve_bases(typing)? {
-            let superclass_hash = s.hash()?;
-            superclass_hashes.push(superclass_hash);
-            self.add_subclass(superclass_hash, type_hash);
+        // The superclass map doubles as the MRO cache, registered types are kept alive so
+        // their hashes cannot be reused by another type until they are removed.
+        if self.superclasses.contains_key(&type_hash) {
+            return Ok(());
+        }
+        let superclass_hashes = match self.derive_mro(typing, type_hash)? {
+            Some(superclass_hashes) => superclass_hashes,
+            None => resolve_bases(typing)?
+                .iter()
+                .map(|s| s.hash())
+                .collect::<PyResult<_>>()?,
+        };
+        for superclass_hash in &superclass_hashes {
+            self.add_subclass(*superclass_hash, type_hash);
         }
         self.superclasses.insert(type_hash, superclass_hashes);
+        self.types
+            .insert(type_hash, Arc::new(typing.clone().unbind()));
         Ok(())
     }
 
+    /// Recompute the subclass and superclass maps from the current MRO of every known type.
+    /// Cached MROs are discarded along with the superclass map.
+    pub fn reindex(&mut self, py: Python) -> PyResult<()> {
+        let types = std::mem::take(&mut self.types);
+        self.subclasses.clear();
+        self.superclasses.clear();
+        for typing in types.values() {
+            self.add(typing.bind(py))?;
+        }
+        Ok(())
+    }
+
+    /// Forget a type, dropping it from the subclass sets of its bases.
+    pub fn remove(&mut self, type_hash: TypeHash) {
+        self.types.remove(&type_hash);
+        let Some(superclasses) = self.superclasses.remove(&type_hash) else {
+            return;
+        };
+        for superclass in superclasses {
+            if let Some(subclasses) = self.subclasses.get_mut(&superclass) {
+                subclasses.remove(&type_hash);
+                if subclasses.is_empty() {
+                    self.subclasses.remove(&superclass);
+                }
+            }
+        }
+    }
+
     pub fn get_superclasses(&self, key: TypeHash) -> Option<&Vec<TypeHash>> {
         self.superclasses.get(&key)
     }
@@ -60,6 +132,68 @@ impl TypeRegistry {
     pub fn get_subclasses(&self, key: TypeHash) -> Option<&HashSet<TypeHash>> {
         self.subclasses.get(&key)
     }
+
+    /// If a value of the provided type is acceptable where the requested type is expected.
+    pub fn is_assignable(
+        &self,
+        provided: TypeHash,
+        requested: TypeHash,
+        specificity: &SolveSpecificity,
+    ) -> bool {
+        let is_subclass = || {
+            self.get_subclasses(requested)
+                .is_some_and(|subclasses| subclasses.contains(&provided))
+        };
+        let is_superclass = || {
+            self.get_superclasses(requested)
+                .is_some_and(|superclasses| superclasses.contains(&provided))
+        };
+        provided == requested
+            || match specificity {
+                SolveSpecificity::Exact => false,
+                SolveSpecificity::AllowSubclass => is_subclass(),
+                SolveSpecificity::AllowSuperclass => is_superclass(),
+                SolveSpecificity::AllowSubclassOrSuperclass => is_subclass() || is_superclass(),
+            }
+    }
+
+    /// Number of MRO hops from the subclass up to the superclass.
+    pub fn mro_distance(&self, subclass: TypeHash, superclass: TypeHash) -> Option<usize> {
+        self.superclasses
+            .get(&subclass)?
+            .iter()
+            .position(|h| *h == superclass)
+    }
+}
+
+#[pyclass(module = "composify.core.registry")]
+pub struct RuleRegistryIter {
+    inner: std::vec::IntoIter<Rule>,
+}
+
+#[pymethods]
+impl RuleRegistryIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<Rule> {
+        slf.inner.next()
+    }
+}
+
+/// What `RuleRegistry.add_rule` does with a duplicate of an already registered rule, see
+/// `Rule::is_duplicate_of`.
+#[pyclass(hash, eq, eq_int, frozen, module = "composify.core.registry")]
+#[derive(PartialEq, Clone, Copy, Debug, Hash, Default)]
+pub enum OnDuplicate {
+    /// Keep the registered rule and skip the new one.
+    #[default]
+    Ignore,
+    /// Raise a `DuplicateRuleError`.
+    Error,
+    /// Register the rule again, each copy provides its own solutions.
+    Allow,
 }
 
 #[pyclass(module = "composify.core.registry")]
@@ -67,9 +201,53 @@ impl TypeRegistry {
 pub struct RuleRegistry {
     rules: HashMap<isize, BinaryHeap<Rule>>,
     types: TypeRegistry,
+    /// Consulted for types without local rules.
+    parent: Option<Arc<RuleRegistry>>,
+    /// Also consult the parent for types with local rules, merging both.
+    merge_parent: bool,
+    /// Results of qualifiers by the metadata they were evaluated on, only for deterministic
+    /// qualifiers.
+    qualifier_cache: Option<Arc<Mutex<QualifierCache>>>,
+    on_duplicate: OnDuplicate,
+}
+
+/// The rejection of each metadata by the qualifiers, None if it qualified.
+type QualifierCache = HashMap<(Qualifiers, MetadataSet), Option<String>>;
+
+/// Canonical name of a rule and why the qualifiers of the requested type rejected it.
+pub type QualifierRejection = (String, String);
+
+/// Canonical name of the rule, name and type of the dependency, and the reason, see
+/// `RuleRegistry.validate`.
+type ValidationIssue = (String, String, TypeInfo, &'static str);
+
+fn validation_issue(rule: &Rule, dependency: &Dependency, reason: &'static str) -> ValidationIssue {
+    (
+        rule.canonical_name.clone(),
+        dependency.name.clone(),
+        dependency.typing.clone(),
+        reason,
+    )
+}
+
+fn duplicate_rule_error(rule: &Rule) -> PyErr {
+    errors::DuplicateRuleError::new_err(format!("Rule {rule} is already registered."))
 }
 
 impl RuleRegistry {
+    /// Add a rule and its output type, unless the duplicate policy rejects it.
+    fn register(&mut self, py: Python, rule: &Rule) -> PyResult<()> {
+        if self.on_duplicate != OnDuplicate::Allow && self.contains_rule(rule) {
+            return match self.on_duplicate {
+                OnDuplicate::Error => Err(duplicate_rule_error(rule)),
+                _ => Ok(()),
+            };
+        }
+        self.types.add(rule.output_type.inner_type.bind(py))?;
+        self.add(rule.clone());
+        Ok(())
+    }
+
     pub fn add(&mut self, rule: Rule) {
         let key = rule.output_type.type_hash;
         let rules = match self.rules.get_mut(&key) {
@@ -82,46 +260,284 @@ impl RuleRegistry {
         rules.push(rule)
     }
 
+    /// If a duplicate of the rule is registered in this registry, parents are not consulted.
+    pub fn contains_rule(&self, rule: &Rule) -> bool {
+        self.rules
+            .get(&rule.output_type.type_hash)
+            .is_some_and(|rules| rules.iter().any(|r| r.is_duplicate_of(rule)))
+    }
+
+    /// Remove a single matching rule, returns false if it was not registered.
+    pub fn remove(&mut self, rule: &Rule) -> bool {
+        let key = rule.output_type.type_hash;
+        let Some(heap) = self.rules.remove(&key) else {
+            return false;
+        };
+        let mut rules = heap.into_vec();
+        let removed = match rules.iter().position(|r| r == rule) {
+            Some(index) => {
+                rules.remove(index);
+                true
+            }
+            None => false,
+        };
+        if rules.is_empty() {
+            self.types.remove(key);
+        } else {
+            self.rules.insert(key, rules.into());
+        }
+        removed
+    }
+
+    /// Get the rules of an output type, falling back to the parent registry if there are none.
     pub fn inner_get(
         &self,
         py: Python,
         key: &TypeHash,
-        attributes: &MetadataSet,
-        qualifiers: &Qualifiers,
+        type_info: &TypeInfo,
     ) -> PyResult<Option<Vec<&Rule>>> {
+        let rules = self.inner_get_local(py, key, type_info)?;
+        let Some(parent) = &self.parent else {
+            return Ok(rules);
+        };
+        match rules {
+            Some(mut rules) if self.merge_parent => {
+                if let Some(inherited) = parent.inner_get(py, key, type_info)? {
+                    rules.extend(inherited);
+                    rules.sort_by(|a, b| b.cmp(a));
+                }
+                Ok(Some(rules))
+            }
+            Some(rules) => Ok(Some(rules)),
+            None => parent.inner_get(py, key, type_info),
+        }
+    }
+
+    fn inner_get_local(
+        &self,
+        py: Python,
+        key: &TypeHash,
+        type_info: &TypeInfo,
+    ) -> PyResult<Option<Vec<&Rule>>> {
+        let attributes = &type_info.attributes;
+        let qualifiers = &type_info.qualifiers;
         let elements = if let Some(elements) = self.rules.get(key) {
             elements
         } else {
             return Ok(None);
         };
-        // TODO: Need to check type Specificity.
-        let mut rules: Vec<&Rule> = elements
-            .iter()
-            .filter(|r| attributes.issubset(&r.output_type.attributes))
-            .collect();
+        let mut rules: Vec<&Rule> = Vec::new();
+        for r in elements.iter() {
+            if r.is_enabled(py)? && attributes.issubset(py, &r.output_type.attributes)? {
+                rules.push(r);
+            }
+        }
         if !qualifiers.is_empty() {
             let mut qualified_rules = Vec::new();
             for e in rules.into_iter() {
-                if qualifiers.qualify(py, &e.output_type.attributes)? {
+                if self
+                    .rejection(py, type_info, &e.output_type.attributes)?
+                    .is_none()
+                {
                     qualified_rules.push(e);
                 }
             }
             rules = qualified_rules;
         }
+        // Heap iteration order is arbitrary, highest priority comes first.
+        rules.sort_by(|a, b| b.cmp(a));
         Ok(Some(rules))
     }
 
+    /// Evaluate the qualifiers, reusing the previous result for the same metadata if cached.
+    /// Returns why the metadata was rejected, None if it qualified.
+    fn rejection(
+        &self,
+        py: Python,
+        requested: &TypeInfo,
+        attributes: &MetadataSet,
+    ) -> PyResult<Option<String>> {
+        let qualifiers = &requested.qualifiers;
+        // Results of contextual qualifiers also depend on the requested type, so are not cached.
+        let cache = match &self.qualifier_cache {
+            Some(cache) if !qualifiers.is_contextual() => cache,
+            _ => return qualifiers.rejection(py, attributes, Some(requested)),
+        };
+        let key = (qualifiers.clone(), attributes.clone());
+        // Cached results are complete values, so a poisoned cache remains usable.
+        let cached = cache
+            .lock_py_attached(py)
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .cloned();
+        if let Some(rejection) = cached {
+            return Ok(rejection);
+        }
+        let rejection = qualifiers.rejection(py, attributes, Some(requested))?;
+        cache
+            .lock_py_attached(py)
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, rejection.clone());
+        Ok(rejection)
+    }
+
+    /// Rules able to provide the type, or its element for collections without rules of their own.
+    fn providers(&self, py: Python, typing: &TypeInfo) -> PyResult<Vec<&Rule>> {
+        match self.get(py, typing)? {
+            Some(rules) if !rules.is_empty() => Ok(rules),
+            _ => match &typing.element {
+                Some(element) => self.providers(py, element),
+                None => Ok(Vec::new()),
+            },
+        }
+    }
+
+    /// Depth-first walk flagging the dependencies whose providers lead back to a rule being
+    /// visited. Rules are identified by address, as several may compare equal.
+    fn find_cycles<'s>(
+        &'s self,
+        py: Python,
+        rule: &'s Rule,
+        states: &mut HashMap<*const Rule, bool>,
+        issues: &mut Vec<ValidationIssue>,
+    ) -> PyResult<()> {
+        states.insert(rule, false);
+        for dependency in rule.dependencies.iter() {
+            let mut cyclic = false;
+            for provider in self.providers(py, &dependency.typing)? {
+                match states.get(&(provider as *const Rule)) {
+                    Some(false) => cyclic = true,
+                    Some(true) => {}
+                    None => self.find_cycles(py, provider, states, issues)?,
+                }
+            }
+            if cyclic {
+                issues.push(validation_issue(rule, dependency, "cycle"));
+            }
+        }
+        states.insert(rule, true);
+        Ok(())
+    }
+
+    /// An empty registry in front of the parent, its rules shadow the parent's for their types.
+    pub fn overlay(parent: Arc<RuleRegistry>) -> Self {
+        RuleRegistry {
+            parent: Some(parent),
+            ..Default::default()
+        }
+    }
+
+    /// Every registered rule grouped by output type, highest priority first within a group.
+    pub fn all_rules(&self) -> Vec<&Rule> {
+        let mut rules: Vec<&Rule> = self.rules.values().flatten().collect();
+        rules.sort_by(|a, b| {
+            (&a.output_type.type_module, &a.output_type.type_name)
+                .cmp(&(&b.output_type.type_module, &b.output_type.type_name))
+                .then_with(|| b.priority.cmp(&a.priority))
+                .then_with(|| a.canonical_name.cmp(&b.canonical_name))
+        });
+        rules
+    }
+
+    /// The rules of this registry followed by those of its parents.
+    fn rules_with_parents(&self) -> Vec<&Rule> {
+        let mut rules: Vec<&Rule> = self.rules.values().flatten().collect();
+        if let Some(parent) = &self.parent {
+            rules.extend(parent.rules_with_parents());
+        }
+        rules
+    }
+
+    /// The type and every type provided by a rule that depends on it, directly or through
+    /// other rules. A dependency counts if the type is related to the requested type in either
+    /// direction, collections by their element type.
+    pub fn dependent_types(&self, type_hash: TypeHash) -> HashSet<TypeHash> {
+        let rules = self.rules_with_parents();
+        let mut dependents = HashSet::from([type_hash]);
+        let mut pending = vec![type_hash];
+        while let Some(provided) = pending.pop() {
+            for rule in &rules {
+                let output = rule.output_type.type_hash;
+                if dependents.contains(&output) {
+                    continue;
+                }
+                let depends = rule.dependencies.iter().any(|dependency| {
+                    let typing = &dependency.typing;
+                    let requested = typing.element.as_deref().unwrap_or(typing).type_hash;
+                    requested == provided
+                        || self.specificity_distance(requested, provided).is_some()
+                });
+                if depends {
+                    dependents.insert(output);
+                    pending.push(output);
+                }
+            }
+        }
+        dependents
+    }
+
+    /// Number of MRO hops between the requested and provided type in either direction.
+    pub fn specificity_distance(&self, requested: TypeHash, provided: TypeHash) -> Option<usize> {
+        self.types
+            .mro_distance(provided, requested)
+            .or_else(|| self.types.mro_distance(requested, provided))
+            .or_else(|| {
+                self.parent
+                    .as_ref()?
+                    .specificity_distance(requested, provided)
+            })
+    }
+
+    /// Superclasses of the type including itself, known by this or a parent registry.
+    fn superclasses(&self, key: TypeHash) -> Option<&Vec<TypeHash>> {
+        self.types
+            .get_superclasses(key)
+            .or_else(|| self.parent.as_ref()?.superclasses(key))
+    }
+
+    /// Subclasses of the type including itself, known by this or a parent registry.
+    fn subclasses(&self, key: TypeHash) -> HashSet<TypeHash> {
+        let mut subclasses = match &self.parent {
+            Some(parent) => parent.subclasses(key),
+            None => HashSet::new(),
+        };
+        if let Some(local) = self.types.get_subclasses(key) {
+            subclasses.extend(local);
+        }
+        subclasses
+    }
+
+    /// If the provided type is a subclass of the requested type in this or a parent registry.
+    fn is_subclass(&self, requested: TypeHash, provided: TypeHash) -> bool {
+        self.types
+            .get_subclasses(requested)
+            .is_some_and(|subclasses| subclasses.contains(&provided))
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_subclass(requested, provided))
+    }
+
+    /// Closest types come first, then the highest priority, then the smallest canonical name.
+    fn sort_by_distance(&self, type_info: &TypeInfo, rules: &mut [&Rule]) {
+        rules.sort_by_cached_key(|r| {
+            (
+                self.specificity_distance(type_info.type_hash, r.output_type.type_hash),
+                Reverse(*r),
+            )
+        });
+    }
+
     /// Get all superclasses including self type.
     pub fn get_super(&self, py: Python, type_info: &TypeInfo) -> PyResult<Option<Vec<&Rule>>> {
-        if let Some(keys) = self.types.get_superclasses(type_info.type_hash) {
+        if let Some(keys) = self.superclasses(type_info.type_hash) {
             let mut rules: Vec<&Rule> = Vec::new();
             for key in keys {
-                if let Some(super_rules) =
-                    self.inner_get(py, key, &type_info.attributes, &type_info.qualifiers)?
-                {
+                if let Some(super_rules) = self.inner_get(py, key, type_info)? {
                     rules.extend(super_rules);
                 }
             }
+            self.sort_by_distance(type_info, &mut rules);
             if rules.is_empty() {
                 Ok(None)
             } else {
@@ -134,15 +550,15 @@ impl RuleRegistry {
 
     /// Get all subclasses including self type.
     pub fn get_sub(&self, py: Python, type_info: &TypeInfo) -> PyResult<Option<Vec<&Rule>>> {
-        if let Some(keys) = self.types.get_subclasses(type_info.type_hash) {
+        let keys = self.subclasses(type_info.type_hash);
+        if !keys.is_empty() {
             let mut rules: Vec<&Rule> = Vec::new();
-            for key in keys {
-                if let Some(super_rules) =
-                    self.inner_get(py, key, &type_info.attributes, &type_info.qualifiers)?
-                {
+            for key in &keys {
+                if let Some(super_rules) = self.inner_get(py, key, type_info)? {
                     rules.extend(super_rules);
                 }
             }
+            self.sort_by_distance(type_info, &mut rules);
             if rules.is_empty() {
                 Ok(None)
             } else {
@@ -153,39 +569,223 @@ impl RuleRegistry {
         }
     }
 
+    /// Get all subclasses and superclasses, the rules of the type itself appear once.
+    pub fn get_either(&self, py: Python, type_info: &TypeInfo) -> PyResult<Option<Vec<&Rule>>> {
+        let mut rules = self.get_sub(py, type_info)?.unwrap_or_default();
+        if let Some(super_rules) = self.get_super(py, type_info)? {
+            for rule in super_rules {
+                if !rules.iter().any(|r| std::ptr::eq(*r, rule)) {
+                    rules.push(rule);
+                }
+            }
+        }
+        self.sort_by_distance(type_info, &mut rules);
+        if rules.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(rules))
+        }
+    }
+
     /// Get exact type.
     pub fn get_exact(&self, py: Python, type_info: &TypeInfo) -> PyResult<Option<Vec<&Rule>>> {
-        self.inner_get(
-            py,
-            &type_info.type_hash,
-            &type_info.attributes,
-            &type_info.qualifiers,
-        )
+        self.inner_get(py, &type_info.type_hash, type_info)
     }
 
     /// Get using the specificity defined in the TypeInfo.
     pub fn get(&self, py: Python, type_info: &TypeInfo) -> PyResult<Option<Vec<&Rule>>> {
-        match type_info.solve_parameter.specificity {
-            SolveSpecificity::Exact => self.get_exact(py, type_info),
-            SolveSpecificity::AllowSubclass => self.get_sub(py, type_info),
-            SolveSpecificity::AllowSuperclass => self.get_super(py, type_info),
+        let rules = match type_info.solve_parameter.specificity {
+            SolveSpecificity::Exact => self.get_exact(py, type_info)?,
+            SolveSpecificity::AllowSubclass => self.get_sub(py, type_info)?,
+            SolveSpecificity::AllowSuperclass => self.get_super(py, type_info)?,
+            SolveSpecificity::AllowSubclassOrSuperclass => self.get_either(py, type_info)?,
+        };
+        let Some(type_args) = &type_info.type_args else {
+            return Ok(rules);
+        };
+        let Some(mut rules) = rules else {
+            return Ok(None);
+        };
+        // A parametrized generic only accepts rules of its origin with the same arguments.
+        rules.retain(|r| {
+            r.output_type.type_hash != type_info.type_hash
+                || r.output_type.type_args.as_ref() == Some(type_args)
+        });
+        if rules.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(rules))
         }
     }
+
+    /// Upper bound of the number of rules providing the type using the specificity defined in
+    /// the TypeInfo. Counted from the rules registered per output type, without evaluating
+    /// `enabled` predicates, attributes or qualifiers.
+    pub fn count_candidates(&self, type_info: &TypeInfo) -> usize {
+        let key = type_info.type_hash;
+        let superclasses = || self.superclasses(key).cloned().unwrap_or_default();
+        let keys: HashSet<TypeHash> = match type_info.solve_parameter.specificity {
+            SolveSpecificity::Exact => HashSet::from([key]),
+            SolveSpecificity::AllowSubclass => self.subclasses(key),
+            SolveSpecificity::AllowSuperclass => superclasses().into_iter().collect(),
+            SolveSpecificity::AllowSubclassOrSuperclass => {
+                let mut keys = self.subclasses(key);
+                keys.extend(superclasses());
+                keys
+            }
+        };
+        keys.iter().map(|key| self.count_rules(key)).sum()
+    }
+
+    /// Number of rules of an output type, following `inner_get` to the parent registry.
+    fn count_rules(&self, key: &TypeHash) -> usize {
+        let local = self.rules.get(key).map(BinaryHeap::len);
+        let Some(parent) = &self.parent else {
+            return local.unwrap_or(0);
+        };
+        match local {
+            Some(local) if self.merge_parent => local + parent.count_rules(key),
+            Some(local) => local,
+            None => parent.count_rules(key),
+        }
+    }
+
+    /// Describe how the output type of a rule matches the requested type.
+    pub fn match_kind(&self, requested: &TypeInfo, rule: &Rule) -> SolveSpecificity {
+        let provided = rule.output_type.type_hash;
+        if provided == requested.type_hash {
+            SolveSpecificity::Exact
+        } else if self.is_subclass(requested.type_hash, provided) {
+            SolveSpecificity::AllowSubclass
+        } else {
+            SolveSpecificity::AllowSuperclass
+        }
+    }
+
+    /// Get using the specificity defined in the TypeInfo, ranking rules providing
+    /// more of the preferred attributes first. Rules without them remain eligible.
+    pub fn get_preferring(
+        &self,
+        py: Python,
+        type_info: &TypeInfo,
+        preferred: &MetadataSet,
+    ) -> PyResult<Option<Vec<&Rule>>> {
+        match self.get(py, type_info)? {
+            Some(rules) => Ok(Some(self.rank_preferring(py, rules, preferred)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Like `get_preferring`, also returning the rules that would provide the type if not for
+    /// its qualifiers, paired with why the qualifiers rejected them. Each qualifier is evaluated
+    /// once per rule, whether or not the reasons end up being reported.
+    pub fn get_explained(
+        &self,
+        py: Python,
+        type_info: &TypeInfo,
+        preferred: &MetadataSet,
+    ) -> PyResult<(Option<Vec<&Rule>>, Vec<QualifierRejection>)> {
+        if type_info.qualifiers.is_empty() {
+            return Ok((self.get_preferring(py, type_info, preferred)?, Vec::new()));
+        }
+        let unqualified = TypeInfo {
+            qualifiers: Qualifiers::default(),
+            ..type_info.clone()
+        };
+        let Some(candidates) = self.get(py, &unqualified)? else {
+            return Ok((None, Vec::new()));
+        };
+        let mut rules = Vec::new();
+        let mut rejections = Vec::new();
+        for rule in candidates {
+            match self.rejection(py, type_info, &rule.output_type.attributes)? {
+                Some(rejection) => rejections.push((rule.canonical_name.clone(), rejection)),
+                None => rules.push(rule),
+            }
+        }
+        let rules = if rules.is_empty() {
+            None
+        } else {
+            Some(self.rank_preferring(py, rules, preferred)?)
+        };
+        Ok((rules, rejections))
+    }
+
+    /// Order the rules providing more of the preferred attributes first.
+    fn rank_preferring<'r>(
+        &self,
+        py: Python,
+        rules: Vec<&'r Rule>,
+        preferred: &MetadataSet,
+    ) -> PyResult<Vec<&'r Rule>> {
+        if preferred.is_empty() {
+            return Ok(rules);
+        }
+        let mut ranked = Vec::with_capacity(rules.len());
+        for rule in rules {
+            ranked.push((
+                preferred.count_matching(py, &rule.output_type.attributes)?,
+                rule,
+            ));
+        }
+        ranked.sort_by(|(a, _), (b, _)| b.cmp(a));
+        Ok(ranked.into_iter().map(|(_, rule)| rule).collect())
+    }
 }
 
 #[pymethods]
 impl RuleRegistry {
+    /// Output types without local rules are looked up in a snapshot of the parent registry,
+    /// later changes to the parent are not seen by the child. Qualifier results are cached per
+    /// metadata if `cache_qualifiers` is set, which is only correct if the qualifiers are
+    /// deterministic.
     #[new]
-    fn __new__() -> RuleRegistry {
-        RuleRegistry::default()
+    #[pyo3(signature = (
+        parent=None,
+        merge_parent=false,
+        cache_qualifiers=false,
+        on_duplicate=OnDuplicate::Ignore,
+    ))]
+    fn __new__(
+        parent: Option<&RuleRegistry>,
+        merge_parent: bool,
+        cache_qualifiers: bool,
+        on_duplicate: OnDuplicate,
+    ) -> RuleRegistry {
+        RuleRegistry {
+            parent: parent.map(|parent| Arc::new(parent.clone())),
+            merge_parent,
+            qualifier_cache: cache_qualifiers.then(Default::default),
+            on_duplicate,
+            ..Default::default()
+        }
+    }
+
+    #[getter]
+    fn parent(&self) -> Option<RuleRegistry> {
+        self.parent.as_deref().cloned()
     }
 
     pub fn add_rule(&mut self, rule: &Bound<Rule>) -> PyResult<()> {
-        // let rule = rule.downcast::<Rule>()?;
-        let py = rule.py();
-        self.types
-            .add(rule.borrow().output_type.inner_type.bind(py))?;
-        self.add(rule.get().clone());
+        self.register(rule.py(), rule.get())
+    }
+
+    /// Register the rules of another registry, following the duplicate policy of this
+    /// registry. Nothing is registered if a duplicate is an error. The parent of the other
+    /// registry is not merged.
+    pub fn merge(slf: &Bound<'_, Self>, other: &Bound<'_, Self>) -> PyResult<()> {
+        let py = slf.py();
+        // Copied before borrowing this registry mutably, the other may be the same registry.
+        let rules: Vec<Rule> = other.borrow().all_rules().into_iter().cloned().collect();
+        let mut registry = slf.borrow_mut();
+        if registry.on_duplicate == OnDuplicate::Error {
+            if let Some(rule) = rules.iter().find(|rule| registry.contains_rule(rule)) {
+                return Err(duplicate_rule_error(rule));
+            }
+        }
+        for rule in &rules {
+            registry.register(py, rule)?;
+        }
         Ok(())
     }
 
@@ -198,13 +798,163 @@ impl RuleRegistry {
         Ok(())
     }
 
+    pub fn remove_rule(&mut self, rule: &Bound<Rule>) -> bool {
+        self.remove(rule.get())
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.types = TypeRegistry::default();
+    }
+
+    /// Recompute the subclass relationships of the registered types, for classes whose bases
+    /// changed after their rules were added. Parent registries are left untouched.
+    pub fn reindex(&mut self, py: Python) -> PyResult<()> {
+        self.types.reindex(py)
+    }
+
+    /// Number of MRO hops between the requested and provided type, None if unrelated.
+    pub fn distance(
+        &self,
+        requested: Bound<'_, PyAny>,
+        provided: Bound<'_, PyAny>,
+    ) -> PyResult<Option<usize>> {
+        Ok(self.specificity_distance(
+            TypeInfo::parse(requested)?.type_hash,
+            TypeInfo::parse(provided)?.type_hash,
+        ))
+    }
+
+    /// If a value of the provided type is acceptable where the requested type is expected, under
+    /// the given specificity or the one annotated on the requested type. The types do not need
+    /// to have any rule registered.
+    #[pyo3(signature = (provided, requested, specificity=None))]
+    pub fn is_assignable(
+        &self,
+        provided: Bound<'_, PyAny>,
+        requested: Bound<'_, PyAny>,
+        specificity: Option<SolveSpecificity>,
+    ) -> PyResult<bool> {
+        let py = provided.py();
+        let provided = TypeInfo::parse(provided)?;
+        let requested = TypeInfo::parse(requested)?;
+        if provided.type_hash == requested.type_hash && provided.type_args != requested.type_args {
+            return Ok(false);
+        }
+        let mut types = TypeRegistry::default();
+        types.add(provided.inner_type.bind(py))?;
+        types.add(requested.inner_type.bind(py))?;
+        Ok(types.is_assignable(
+            provided.type_hash,
+            requested.type_hash,
+            &specificity.unwrap_or(requested.solve_parameter.specificity),
+        ))
+    }
+
+    /// Check every registered rule without solving. Returns `(rule, dependency, type, reason)`
+    /// for each required dependency without any provider, reason "missing", and for each
+    /// dependency with a provider leading back to the rule, reason "cycle".
+    pub fn validate(&self, py: Python) -> PyResult<Vec<ValidationIssue>> {
+        let rules = self.all_rules();
+        let mut issues = Vec::new();
+        for rule in rules.iter() {
+            for dependency in rule.dependencies.iter() {
+                if dependency.optional
+                    || dependency.typing.solve_parameter.cardinality == SolveCardinality::AtMostOne
+                {
+                    continue;
+                }
+                if self.providers(py, &dependency.typing)?.is_empty() {
+                    issues.push(validation_issue(rule, dependency, "missing"));
+                }
+            }
+        }
+        let mut states = HashMap::new();
+        for rule in rules {
+            if !states.contains_key(&(rule as *const Rule)) {
+                self.find_cycles(py, rule, &mut states, &mut issues)?;
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Whether any rule is registered for the exact output type.
+    pub fn contains(&self, type_info: Bound<'_, PyAny>) -> PyResult<bool> {
+        Ok(self
+            .rules
+            .contains_key(&TypeInfo::parse(type_info)?.type_hash))
+    }
+
+    /// Number of rules registered for the exact output type.
+    pub fn rule_count_for(&self, type_info: Bound<'_, PyAny>) -> PyResult<usize> {
+        Ok(self
+            .rules
+            .get(&TypeInfo::parse(type_info)?.type_hash)
+            .map_or(0, |rules| rules.len()))
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyResult<Py<RuleRegistryIter>> {
+        let rules: Vec<Rule> = slf.all_rules().into_iter().cloned().collect();
+        Py::new(
+            slf.py(),
+            RuleRegistryIter {
+                inner: rules.into_iter(),
+            },
+        )
+    }
+
+    fn __len__(&self) -> usize {
+        self.rules.values().map(|rules| rules.len()).sum()
+    }
+
+    /// Every registered rule carrying the tag, in iteration order.
+    pub fn rules_with_tag(&self, tag: &str) -> Vec<Rule> {
+        self.all_rules()
+            .into_iter()
+            .filter(|rule| rule.tags.contains(tag))
+            .cloned()
+            .collect()
+    }
+
+    /// Every registered rule with a dependency a provider of the type could satisfy, under
+    /// the specificity of the dependency. Collection dependencies match on their element type.
+    pub fn dependents_of(&self, type_info: Bound<'_, PyAny>) -> PyResult<Vec<Rule>> {
+        let py = type_info.py();
+        let provided = TypeInfo::parse(type_info)?;
+        let mut types = TypeRegistry::default();
+        types.add(provided.inner_type.bind(py))?;
+        let mut dependents = Vec::new();
+        for rule in self.all_rules() {
+            for dependency in rule.dependencies.iter() {
+                let requested = dependency
+                    .typing
+                    .element
+                    .as_deref()
+                    .unwrap_or(&dependency.typing);
+                types.add(requested.inner_type.bind(py))?;
+                if types.is_assignable(
+                    provided.type_hash,
+                    requested.type_hash,
+                    &requested.solve_parameter.specificity,
+                ) {
+                    dependents.push(rule.clone());
+                    break;
+                }
+            }
+        }
+        Ok(dependents)
+    }
+
+    #[pyo3(signature = (type_info, prefer_attributes=None))]
     pub fn get_rules<'py>(
         &mut self,
         type_info: Bound<'py, PyAny>,
+        prefer_attributes: Option<Vec<Bound<'py, PyAny>>>,
     ) -> PyResult<Option<Bound<'py, PyTuple>>> {
         let py = type_info.py();
         let key = TypeInfo::parse(type_info)?;
-        let rules: Vec<Rule> = match self.get(py, &key)? {
+        let preferred = MetadataSet::new(prefer_attributes.unwrap_or_default())?;
+        let rules: Vec<Rule> = match self.get_preferring(py, &key, &preferred)? {
             Some(e) => e.iter().map(|r| (*r).clone()).collect(),
             None => return Ok(None),
         };
@@ -1,11 +1,17 @@
+use pyo3::exceptions::PyKeyError;
 use pyo3::prelude::*;
-use pyo3::types::{PyMapping, PyString};
+use pyo3::types::{PyDict, PyFrozenSet, PyMapping, PyString};
+use pyo3::{intern, types::PyType};
 
+use std::collections::BTreeSet;
 use std::fmt::{Display, Write};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::slice::Iter;
 use std::sync::Arc;
 
+use crate::errors;
+use crate::registry::RuleRegistry;
+use crate::solve_parameters::{DependencyMarker, SolveCardinality};
 use crate::type_info::TypeInfo;
 
 #[pyclass(get_all, frozen, eq, module = "composify.core.rules")]
@@ -13,15 +19,53 @@ use crate::type_info::TypeInfo;
 pub struct Dependency {
     pub name: String,
     pub typing: TypeInfo,
+    /// Bound to every solution of its type as a single list argument.
+    pub variadic: bool,
+    /// Left out of the arguments if unresolved, instead of dropping the rule.
+    pub optional: bool,
+}
+
+/// Find the dependency markers in the `Annotated` metadata of a type.
+fn parse_markers(typing: &Bound<'_, PyAny>) -> PyResult<Vec<DependencyMarker>> {
+    let py = typing.py();
+    let mut markers = Vec::new();
+    if typing.hasattr(intern!(py, "__metadata__"))? {
+        for element in typing.getattr(intern!(py, "__metadata__"))?.try_iter()? {
+            if let Ok(marker) = element?.downcast::<DependencyMarker>() {
+                markers.push(marker.get().clone());
+            }
+        }
+    }
+    Ok(markers)
 }
 
 #[pymethods]
 impl Dependency {
+    /// The typing is either an annotation or an already parsed `TypeInfo`, which is used as
+    /// is. A `TypeInfo` carries no dependency markers.
     #[new]
     fn new(name: Bound<'_, PyString>, typing: Bound<'_, PyAny>) -> PyResult<Self> {
+        if let Ok(type_info) = typing.downcast::<TypeInfo>() {
+            let typing = type_info.get().clone();
+            return Ok(Dependency {
+                name: String::from(name.to_str()?),
+                optional: typing.optional,
+                typing,
+                variadic: false,
+            });
+        }
+        let markers = parse_markers(&typing)?;
+        let variadic = markers.contains(&DependencyMarker::Variadic);
+        let mut typing = TypeInfo::parse(typing)?;
+        let optional = typing.optional || markers.contains(&DependencyMarker::Optional);
+        if variadic {
+            typing.solve_parameter.cardinality = SolveCardinality::Exhaustive;
+        }
         Ok(Dependency {
             name: String::from(name.to_str()?),
-            typing: TypeInfo::parse(typing)?,
+            typing,
+            variadic,
+            optional,
         })
     }
 
@@ -31,15 +75,21 @@ impl Dependency {
 
     fn __hash__(slf: PyRef<'_, Self>) -> PyResult<u64> {
         let mut hasher = DefaultHasher::new();
-        slf.name.hash(&mut hasher);
-        slf.typing.hash(&mut hasher);
+        slf.hash(&mut hasher);
         Ok(hasher.finish())
     }
 }
 
 impl Display for Dependency {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Dependency({}, type={})", &self.name, &self.typing,)
+        write!(f, "Dependency({}, type={}", &self.name, &self.typing)?;
+        if self.variadic {
+            write!(f, ", variadic")?;
+        }
+        if self.optional {
+            write!(f, ", optional")?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -47,12 +97,17 @@ impl Hash for Dependency {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
         self.typing.hash(state);
+        self.variadic.hash(state);
+        self.optional.hash(state);
     }
 }
 
 impl PartialEq for Dependency {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.typing == other.typing
+        self.name == other.name
+            && self.typing == other.typing
+            && self.variadic == other.variadic
+            && self.optional == other.optional
     }
 }
 
@@ -72,8 +127,9 @@ impl DependenciesIter {
     }
 }
 
+/// Sorted by name, so rules with the same parameters compare equal whatever their order.
 #[pyclass(frozen, eq, hash, module = "composify.core.rules")]
-#[derive(Debug, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Dependencies {
     pub dependencies: Vec<Dependency>,
 }
@@ -102,17 +158,43 @@ impl Dependencies {
         Py::new(slf.py(), iter)
     }
 
+    /// The dependency with the parameter name, None if there is none.
+    pub fn get(&self, name: &str) -> Option<Dependency> {
+        self.find(name).cloned()
+    }
+
+    fn __getitem__(&self, name: &str) -> PyResult<Dependency> {
+        self.get(name)
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.dependencies.len()
+    }
+
     fn __repr__(&self) -> PyResult<String> {
         Ok(self.to_string())
     }
 }
 
 impl Dependencies {
+    /// Dependencies are sorted by name on construction.
+    pub fn find(&self, name: &str) -> Option<&Dependency> {
+        self.dependencies
+            .binary_search_by(|dependency| dependency.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.dependencies[index])
+    }
+
     pub fn is_empty(&self) -> bool {
         self.dependencies.is_empty()
     }
 
-    pub fn iter(&self) -> Iter<Dependency> {
+    pub fn iter(&self) -> Iter<'_, Dependency> {
         self.dependencies.iter()
     }
 }
@@ -166,11 +248,25 @@ pub struct Rule {
     pub priority: i32,
     #[pyo3(get)]
     pub is_async: bool,
+    /// Called with the current resolution path, the rule is skipped if it returns false.
+    pub guard: Option<Arc<Py<PyAny>>>,
+    /// Labels for grouping rules, not considered during solving.
+    pub tags: BTreeSet<String>,
+    /// Called without arguments on lookup, the rule is hidden while it returns false.
+    pub enabled: Option<Arc<Py<PyAny>>>,
+    #[pyo3(get)]
+    pub module: Option<String>,
+    #[pyo3(get)]
+    pub qualname: Option<String>,
+    #[pyo3(get)]
+    pub lineno: Option<u32>,
 }
 
 #[pymethods]
 impl Rule {
     #[new]
+    #[pyo3(signature = (function, canonical_name, output_type, dependencies, priority, is_async, guard=None, tags=None, enabled=None))]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         function: Bound<'_, PyAny>,
         canonical_name: String,
@@ -178,7 +274,15 @@ impl Rule {
         dependencies: Bound<'_, PyAny>,
         priority: i32,
         is_async: bool,
+        guard: Option<Bound<'_, PyAny>>,
+        tags: Option<Bound<'_, PyAny>>,
+        enabled: Option<Bound<'_, PyAny>>,
     ) -> PyResult<Self> {
+        let py = function.py();
+        let partial = py
+            .import(intern!(py, "functools"))?
+            .getattr(intern!(py, "partial"))?;
+        let (module, qualname, lineno) = source_location(&unwrap_callable(&function, &partial)?);
         Ok(Self {
             function: Arc::new(function.into()),
             canonical_name,
@@ -190,6 +294,15 @@ impl Rule {
             },
             priority,
             is_async,
+            guard: guard.map(|g| Arc::new(g.unbind())),
+            tags: match tags {
+                Some(tags) => parse_tags(&tags)?,
+                None => BTreeSet::new(),
+            },
+            enabled: enabled.map(|e| Arc::new(e.unbind())),
+            module,
+            qualname,
+            lineno,
         })
     }
 
@@ -201,20 +314,295 @@ impl Rule {
     pub fn get_function(&self, py: Python) -> Py<PyAny> {
         self.function.clone_ref(py)
     }
+
+    #[getter(guard)]
+    pub fn get_guard(&self, py: Python) -> Option<Py<PyAny>> {
+        self.guard.as_ref().map(|g| g.clone_ref(py))
+    }
+
+    #[getter(enabled)]
+    pub fn get_enabled(&self, py: Python) -> Option<Py<PyAny>> {
+        self.enabled.as_ref().map(|e| e.clone_ref(py))
+    }
+
+    #[getter(tags)]
+    pub fn get_tags<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyFrozenSet>> {
+        PyFrozenSet::new(py, &self.tags)
+    }
+
+    /// A copy of the rule with another priority.
+    pub fn with_priority(&self, priority: i32) -> Self {
+        Self {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the rule with another canonical name.
+    pub fn with_canonical_name(&self, canonical_name: String) -> Self {
+        Self {
+            canonical_name,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the rule with the tags replaced.
+    pub fn with_tags(&self, tags: Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            tags: parse_tags(&tags)?,
+            ..self.clone()
+        })
+    }
+
+    /// A copy of the rule with the guard replaced, None removes it.
+    pub fn with_guard(&self, guard: Option<Bound<'_, PyAny>>) -> Self {
+        Self {
+            guard: guard.map(|g| Arc::new(g.unbind())),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the rule with the enabled predicate replaced, None removes it.
+    pub fn with_enabled(&self, enabled: Option<Bound<'_, PyAny>>) -> Self {
+        Self {
+            enabled: enabled.map(|e| Arc::new(e.unbind())),
+            ..self.clone()
+        }
+    }
+
+    /// If every dependency has a rule in the registry, or may be left unresolved. Only the
+    /// direct dependencies are checked, nothing is solved.
+    pub fn is_satisfiable_by(&self, py: Python, registry: &RuleRegistry) -> PyResult<bool> {
+        for dependency in self.dependencies.iter() {
+            let typing = &dependency.typing;
+            if dependency.optional
+                || typing.optional
+                || typing.solve_parameter.cardinality == SolveCardinality::AtMostOne
+            {
+                continue;
+            }
+            // Collections are solved through their element.
+            let target = typing.element.as_deref().unwrap_or(typing);
+            if registry
+                .get(py, target)?
+                .is_none_or(|rules| rules.is_empty())
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Collect the tags from an iterable of strings, a lone string is a single tag.
+fn parse_tags(tags: &Bound<'_, PyAny>) -> PyResult<BTreeSet<String>> {
+    if let Ok(tag) = tags.downcast::<PyString>() {
+        return Ok(BTreeSet::from([tag.to_str()?.to_owned()]));
+    }
+    tags.try_iter()?.map(|tag| tag?.extract()).collect()
+}
+
+/// Read where the function was defined, attributes the object lacks are left empty.
+fn source_location(function: &Bound<'_, PyAny>) -> (Option<String>, Option<String>, Option<u32>) {
+    let py = function.py();
+    let module = function
+        .getattr(intern!(py, "__module__"))
+        .and_then(|m| m.extract())
+        .ok();
+    let qualname = function
+        .getattr(intern!(py, "__qualname__"))
+        .and_then(|q| q.extract())
+        .ok();
+    let lineno = function
+        .getattr(intern!(py, "__code__"))
+        .and_then(|c| c.getattr(intern!(py, "co_firstlineno")))
+        .and_then(|l| l.extract())
+        .ok();
+    (module, qualname, lineno)
+}
+
+impl Rule {
+    /// Where the rule was defined as `module:lineno`, None if the module is unknown.
+    pub fn location(&self) -> Option<String> {
+        let module = self.module.as_ref()?;
+        Some(match self.lineno {
+            Some(lineno) => format!("{module}:{lineno}"),
+            None => module.clone(),
+        })
+    }
+
+    /// Invoke the guard with the current resolution path, rules without guard always pass.
+    pub fn check_guard(&self, py: Python, path: Vec<TypeInfo>) -> PyResult<bool> {
+        match &self.guard {
+            Some(guard) => guard.bind(py).call1((path,))?.is_truthy(),
+            None => Ok(true),
+        }
+    }
+
+    /// Invoke the enabled predicate, rules without one are always enabled.
+    pub fn is_enabled(&self, py: Python) -> PyResult<bool> {
+        match &self.enabled {
+            Some(enabled) => enabled.bind(py).call0()?.is_truthy(),
+            None => Ok(true),
+        }
+    }
+}
+
+/// The function underlying partials and bound methods.
+fn unwrap_callable<'py>(
+    function: &Bound<'py, PyAny>,
+    partial: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let py = function.py();
+    let inspect = py.import(intern!(py, "inspect"))?;
+    let mut function = function.clone();
+    loop {
+        function = if function.is_instance(partial)? {
+            function.getattr(intern!(py, "func"))?
+        } else if inspect
+            .call_method1(intern!(py, "ismethod"), (&function,))?
+            .is_truthy()?
+        {
+            function.getattr(intern!(py, "__func__"))?
+        } else {
+            return Ok(function);
+        };
+    }
+}
+
+/// Create a rule from an annotated function or class. The output type is taken from the
+/// return annotation, or the class itself, and each parameter becomes a dependency. Arguments
+/// bound by a partial and the self of a bound method are excluded.
+#[pyfunction]
+#[pyo3(signature = (function, *, canonical_name=None, output_type=None, priority=0, guard=None, tags=None, enabled=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn make_rule(
+    function: Bound<'_, PyAny>,
+    canonical_name: Option<String>,
+    output_type: Option<Bound<'_, PyAny>>,
+    priority: i32,
+    guard: Option<Bound<'_, PyAny>>,
+    tags: Option<Bound<'_, PyAny>>,
+    enabled: Option<Bound<'_, PyAny>>,
+) -> PyResult<Rule> {
+    let py = function.py();
+    let inspect = py.import(intern!(py, "inspect"))?;
+    let is_class = function.is_instance_of::<PyType>();
+    let partial = py
+        .import(intern!(py, "functools"))?
+        .getattr(intern!(py, "partial"))?;
+    let unwrapped = unwrap_callable(&function, &partial)?;
+    let annotated = if is_class {
+        function.getattr(intern!(py, "__init__"))?
+    } else {
+        unwrapped.clone()
+    };
+    let kwargs = PyDict::new(py);
+    kwargs.set_item(intern!(py, "include_extras"), true)?;
+    let type_hints = py
+        .import(intern!(py, "typing"))?
+        .getattr(intern!(py, "get_type_hints"))?
+        .call((&annotated,), Some(&kwargs))?
+        .downcast_into::<PyDict>()?;
+
+    let canonical_name = match canonical_name {
+        Some(name) => name,
+        None => format!(
+            "{}.{}",
+            unwrapped.getattr(intern!(py, "__module__"))?,
+            unwrapped.getattr(intern!(py, "__qualname__"))?
+        )
+        .replace(".<locals>", ""),
+    };
+    let output_type = match output_type {
+        Some(output_type) => output_type,
+        None if is_class => function.clone(),
+        None => type_hints.get_item("return")?.ok_or_else(|| {
+            errors::MissingReturnTypeAnnotation::new_err(format!(
+                "@rule {canonical_name} return is missing a type annotation."
+            ))
+        })?,
+    };
+
+    let bound = if function.is_instance(&partial)? {
+        Some(function.getattr(intern!(py, "keywords"))?)
+    } else {
+        None
+    };
+    let dependencies = PyDict::new(py);
+    let parameters = inspect
+        .call_method1(intern!(py, "signature"), (&function,))?
+        .getattr(intern!(py, "parameters"))?;
+    for parameter in parameters.try_iter()? {
+        let parameter = parameter?;
+        if let Some(bound) = &bound {
+            if bound.contains(&parameter)? {
+                continue;
+            }
+        }
+        let typing = type_hints.get_item(&parameter)?.ok_or_else(|| {
+            errors::MissingParameterTypeAnnotation::new_err(format!(
+                "@rule {canonical_name} parameter {parameter} is missing a type annotation."
+            ))
+        })?;
+        dependencies.set_item(parameter, typing)?;
+    }
+
+    let is_async = inspect
+        .call_method1(intern!(py, "iscoroutinefunction"), (&function,))?
+        .is_truthy()?;
+    Rule::new(
+        function,
+        canonical_name,
+        output_type,
+        dependencies.into_any(),
+        priority,
+        is_async,
+        guard,
+        tags,
+        enabled,
+    )
 }
 
 impl Display for Rule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Rule({}, {}, out={}, priority={}, is_async={}, dependencies={})",
+            "Rule({}, {}, out={}, priority={}, is_async={}, dependencies={}",
             self.canonical_name,
             self.function,
             self.output_type,
             self.priority,
             self.is_async,
             self.dependencies,
-        )
+        )?;
+        if !self.tags.is_empty() {
+            let tags: Vec<&str> = self.tags.iter().map(String::as_str).collect();
+            write!(f, ", tags={{{}}}", tags.join(", "))?;
+        }
+        if let Some(location) = self.location() {
+            write!(f, ", defined_at={location}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Rule {
+    /// Equal, and wrapping the same function, guard and enabled predicate with the same tags.
+    /// Distinct functions sharing a canonical name, such as closures built in a loop, are not
+    /// duplicates.
+    pub fn is_duplicate_of(&self, other: &Rule) -> bool {
+        fn same(a: &Option<Arc<Py<PyAny>>>, b: &Option<Arc<Py<PyAny>>>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a.as_ptr() == b.as_ptr(),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+        }
+        self == other
+            && self.function.as_ptr() == other.function.as_ptr()
+            && same(&self.guard, &other.guard)
+            && same(&self.enabled, &other.enabled)
+            && self.tags == other.tags
     }
 }
 
@@ -236,9 +624,13 @@ impl PartialOrd for Rule {
     }
 }
 
+/// Higher priority rules are greater, ties are broken by the lexicographically smaller
+/// canonical name being greater.
 impl Ord for Rule {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.priority.cmp(&other.priority)
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.canonical_name.cmp(&self.canonical_name))
     }
 }
 
@@ -1,11 +1,22 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::{Display, Write},
     hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
 };
 
-use pyo3::{exceptions::PyIndexError, prelude::*, types::PyMapping};
+use pyo3::{
+    exceptions::{PyIndexError, PyKeyError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyMapping, PyString},
+};
 
-use crate::{rules::Rule, type_info::TypeInfo};
+use crate::{
+    errors,
+    rules::{Dependencies, Rule},
+    solve_parameters::SolveSpecificity,
+    type_info::TypeInfo,
+};
 
 #[pyclass(get_all, frozen, eq, hash, module = "composify.core.solutions")]
 #[derive(Hash, PartialEq, Eq, Debug, Clone)]
@@ -57,7 +68,7 @@ impl SolutionArgsCollectionIter {
 
 #[pyclass(frozen, sequence, eq, hash, module = "composify.core.solutions")]
 #[derive(Default, Debug, Clone)]
-pub struct SolutionArgsCollection(pub Vec<SolutionArg>, pub u64);
+pub struct SolutionArgsCollection(pub Arc<Vec<SolutionArg>>, pub u64);
 
 impl SolutionArgsCollection {
     pub fn new(mut args: Vec<SolutionArg>) -> Self {
@@ -68,18 +79,37 @@ impl SolutionArgsCollection {
             args.hash(&mut hasher);
             h = hasher.finish();
         }
-        Self(args, h)
+        Self(Arc::new(args), h)
     }
 }
 
-impl SolutionArgsCollection {
-    pub fn add(&mut self, arg: SolutionArg) {
-        self.0.push(arg);
+impl Drop for SolutionArgsCollection {
+    fn drop(&mut self) {
+        // Unwind uniquely owned descendants iteratively, deep solution chains would otherwise
+        // overflow the native stack.
+        let mut pending = vec![std::mem::take(&mut self.0)];
+        while let Some(args) = pending.pop() {
+            if let Ok(args) = Arc::try_unwrap(args) {
+                for mut arg in args {
+                    pending.push(std::mem::take(&mut arg.solution.args.0));
+                }
+            }
+        }
     }
+}
 
+impl SolutionArgsCollection {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// The argument with the parameter name, the arguments are sorted by name.
+    pub fn find(&self, name: &str) -> Option<&SolutionArg> {
+        self.0
+            .binary_search_by(|arg| arg.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.0[index])
+    }
 }
 
 #[pymethods]
@@ -106,7 +136,7 @@ impl SolutionArgsCollection {
 
     pub fn __iter__(&self, py: Python) -> PyResult<Py<SolutionArgsCollectionIter>> {
         let iter = SolutionArgsCollectionIter {
-            inner: self.clone().0.into_iter(),
+            inner: self.0.as_ref().clone().into_iter(),
         };
         Py::new(py, iter)
     }
@@ -115,17 +145,47 @@ impl SolutionArgsCollection {
         Ok(self.to_string())
     }
 
-    pub fn __getitem__(&self, i: usize) -> PyResult<SolutionArg> {
+    /// Look up by parameter name for a string key, or by position otherwise.
+    pub fn __getitem__(&self, key: &Bound<'_, PyAny>) -> PyResult<SolutionArg> {
+        if let Ok(name) = key.downcast::<PyString>() {
+            let name = name.to_str()?;
+            return self
+                .find(name)
+                .cloned()
+                .ok_or_else(|| PyKeyError::new_err(name.to_string()));
+        }
+        let i: usize = key.extract()?;
         match self.0.get(i) {
             Some(val) => Ok(val.clone()),
             None => Err(PyIndexError::new_err(format!("Index out of range: {}", i))),
         }
     }
+
+    /// The argument with the parameter name, None if there is none.
+    pub fn get(&self, name: &str) -> Option<SolutionArg> {
+        self.find(name).cloned()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.0.iter().map(|arg| arg.name.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<Solution> {
+        self.0.iter().map(|arg| arg.solution.clone()).collect()
+    }
+
+    pub fn items(&self) -> Vec<(String, Solution)> {
+        self.0
+            .iter()
+            .map(|arg| (arg.name.clone(), arg.solution.clone()))
+            .collect()
+    }
 }
 
+/// The hash ignores canonical names of rules, so colliding collections are compared in full.
 impl PartialEq for SolutionArgsCollection {
     fn eq(&self, other: &Self) -> bool {
-        self.1 == other.1
+        self.1 == other.1 && (Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0)
     }
 }
 
@@ -163,6 +223,10 @@ impl Display for SolutionArgsCollection {
 pub struct Solution {
     pub rule: Rule,
     pub args: SolutionArgsCollection,
+    /// How the rule output matched the requested type, if produced by the solver.
+    pub match_kind: Option<SolveSpecificity>,
+    /// Placeholder for a dependency that was not resolved, see `Solver.solve_for` preview mode.
+    pub is_unresolved: bool,
 }
 
 #[pymethods]
@@ -177,6 +241,8 @@ impl Solution {
             } else {
                 SolutionArgsCollection::default()
             },
+            match_kind: None,
+            is_unresolved: false,
         })
     }
 
@@ -195,6 +261,182 @@ impl Solution {
         self.rule.is_async
     }
 
+    /// If both solutions share the same root rule, regardless of their arguments.
+    pub fn equals_ignoring_args(&self, other: &Solution) -> bool {
+        self.rule == other.rule
+    }
+
+    /// Flatten the solution tree into build steps where dependencies precede their dependents.
+    /// Shared dependencies appear only once.
+    pub fn build_plan(&self) -> PyResult<Vec<Solution>> {
+        let mut plan = Vec::new();
+        self.post_order(
+            |solution| Ok(solution.args.0.iter().map(|arg| &arg.solution).collect()),
+            |solution| {
+                plan.push(solution.clone());
+                Ok(())
+            },
+        )?;
+        Ok(plan)
+    }
+
+    /// Construct the object by calling the functions of the tree, dependencies first. Arguments
+    /// named in the overrides are injected instead of built, and shared solutions are built once.
+    #[pyo3(signature = (overrides=None))]
+    pub fn build(&self, py: Python, overrides: Option<&Bound<'_, PyDict>>) -> PyResult<Py<PyAny>> {
+        let overridden = |arg: &SolutionArg| match overrides {
+            Some(overrides) => overrides.get_item(&arg.name),
+            None => Ok(None),
+        };
+        let mut built: HashMap<&Solution, Py<PyAny>> = HashMap::new();
+        self.post_order(
+            |solution| {
+                if solution.is_unresolved {
+                    return Err(PyValueError::new_err(format!(
+                        "Trying to build from unresolved solution {}",
+                        solution
+                    )));
+                }
+                if solution.rule.is_async {
+                    return Err(errors::AsyncSolutionError::new_err(format!(
+                        "Trying to build from async solution {}",
+                        solution
+                    )));
+                }
+                let mut deps = Vec::new();
+                for arg in solution.args.0.iter() {
+                    if overridden(arg)?.is_none() {
+                        deps.push(&arg.solution);
+                    }
+                }
+                Ok(deps)
+            },
+            |solution| {
+                let kwargs = PyDict::new(py);
+                for arg in solution.args.0.iter() {
+                    match overridden(arg)? {
+                        Some(value) => kwargs.set_item(&arg.name, value)?,
+                        None => kwargs.set_item(&arg.name, &built[&arg.solution])?,
+                    }
+                }
+                let value = solution.rule.function.call(py, (), Some(&kwargs))?;
+                built.insert(solution, value);
+                Ok(())
+            },
+        )?;
+        Ok(built.remove(self).expect("the root is walked last"))
+    }
+
+    /// Sum of the rule priorities of every node in the tree, shared dependencies count once per
+    /// use, saturating at the bounds of i64. Exhaustive results of the same type distance are
+    /// ordered by it, highest first.
+    pub fn score(&self) -> i64 {
+        // Saturates, exponentially many uses of shared dependencies would overflow otherwise.
+        self.fold(|solution, args: &[i64]| {
+            args.iter()
+                .fold(i64::from(solution.rule.priority), |score, arg| {
+                    score.saturating_add(*arg)
+                })
+        })
+    }
+
+    /// Number of nodes on the longest path from this solution to a leaf, a leaf has depth 1.
+    pub fn depth(&self) -> usize {
+        self.fold(|_, args: &[usize]| 1 + args.iter().max().copied().unwrap_or(0))
+    }
+
+    /// Number of nodes in the tree. Shared dependencies count once per use, or once in total
+    /// if unique.
+    #[pyo3(signature = (unique=false))]
+    pub fn node_count(&self, unique: bool) -> usize {
+        let mut count = 0;
+        let mut seen: HashSet<&Solution> = HashSet::new();
+        let mut pending = vec![self];
+        while let Some(solution) = pending.pop() {
+            if unique && !seen.insert(solution) {
+                continue;
+            }
+            count += 1;
+            pending.extend(solution.args.0.iter().map(|arg| &arg.solution));
+        }
+        count
+    }
+
+    /// Deterministic hex digest of the solution tree, stable across processes. Covers the rule
+    /// names, output type names and solve parameters of every node, and the argument names.
+    pub fn fingerprint(&self) -> String {
+        let fingerprint = self.fold(|solution, args: &[u64]| {
+            let mut hasher = Fnv1a::default();
+            hasher.write_field(&solution.rule.canonical_name);
+            hasher.write_field(&solution.rule.output_type.canonical_name());
+            hasher.write_field(&solution.rule.output_type.solve_parameter.to_string());
+            for (arg, fingerprint) in solution.args.0.iter().zip(args) {
+                hasher.write_field(&arg.name);
+                hasher.write_u64(*fingerprint);
+            }
+            hasher.finish()
+        });
+        format!("{:016x}", fingerprint)
+    }
+
+    /// Convert the solution tree into JSON-compatible nested dicts.
+    /// Functions are represented by the canonical name of their rule.
+    /// Shared solutions share a dict.
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let mut dicts: HashMap<&Solution, Bound<'py, PyDict>> = HashMap::new();
+        self.post_order(
+            |solution| Ok(solution.args.0.iter().map(|arg| &arg.solution).collect()),
+            |solution| {
+                let args = PyDict::new(py);
+                for arg in solution.args.0.iter() {
+                    args.set_item(&arg.name, &dicts[&arg.solution])?;
+                }
+                let dict = PyDict::new(py);
+                dict.set_item("canonical_name", &solution.rule.canonical_name)?;
+                dict.set_item("output_type", solution.rule.output_type.to_type_string())?;
+                dict.set_item("is_async", solution.rule.is_async)?;
+                dict.set_item("args", args)?;
+                dicts.insert(solution, dict);
+                Ok(())
+            },
+        )?;
+        Ok(dicts.remove(self).expect("the root is walked last"))
+    }
+
+    /// Render the solution tree as a Graphviz DOT graph, shared solutions appear once.
+    pub fn to_dot(&self) -> String {
+        let mut ids: HashMap<&Solution, usize> = HashMap::new();
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        ids.insert(self, 0);
+        let mut pending = vec![self];
+        while let Some(solution) = pending.pop() {
+            let id = ids[solution];
+            let _ = writeln!(
+                nodes,
+                "    n{} [label=\"{}\\n{}\"];",
+                id,
+                escape_dot(&solution.rule.canonical_name),
+                escape_dot(&solution.rule.output_type.to_type_string()),
+            );
+            for arg in solution.args.0.iter() {
+                let next = ids.len();
+                let child = *ids.entry(&arg.solution).or_insert_with(|| {
+                    pending.push(&arg.solution);
+                    next
+                });
+                let _ = writeln!(
+                    edges,
+                    "    n{} -> n{} [label=\"{}\"];",
+                    id,
+                    child,
+                    escape_dot(&arg.name)
+                );
+            }
+        }
+        format!("digraph Solution {{\n{}{}}}\n", nodes, edges)
+    }
+
     pub fn __repr__(&self) -> PyResult<String> {
         Ok(format!(
             "Solution(rule={}, arguments={})",
@@ -207,6 +449,134 @@ impl Solution {
     }
 }
 
+impl Solution {
+    pub const UNRESOLVED_RULE_NAME: &'static str = "__unresolved__";
+
+    /// Create a placeholder standing in for an unresolved dependency of the given type.
+    pub fn unresolved(py: Python, typing: &TypeInfo) -> Self {
+        Self {
+            rule: Rule {
+                function: Arc::new(py.None()),
+                canonical_name: Self::UNRESOLVED_RULE_NAME.to_string(),
+                output_type: typing.clone(),
+                dependencies: Dependencies::default(),
+                priority: 0,
+                is_async: false,
+                guard: None,
+                tags: Default::default(),
+                enabled: None,
+                module: None,
+                qualname: None,
+                lineno: None,
+            },
+            args: SolutionArgsCollection::default(),
+            match_kind: None,
+            is_unresolved: true,
+        }
+    }
+
+    /// If any solution in the tree, including this one, matches the predicate.
+    pub fn any_node(&self, mut predicate: impl FnMut(&Solution) -> bool) -> bool {
+        let mut pending = vec![self];
+        while let Some(solution) = pending.pop() {
+            if predicate(solution) {
+                return true;
+            }
+            pending.extend(solution.args.0.iter().map(|arg| &arg.solution));
+        }
+        false
+    }
+
+    /// Combine the values of the arguments into the value of each node, bottom up. Every
+    /// distinct node is combined once, so shared dependencies do not multiply the work, and
+    /// the walk is iterative so deep trees do not exhaust the stack.
+    fn fold<'a, T: Copy>(&'a self, combine: impl Fn(&Solution, &[T]) -> T) -> T {
+        let mut values: HashMap<&'a Solution, T> = HashMap::new();
+        let mut pending = vec![(self, false)];
+        while let Some((solution, expanded)) = pending.pop() {
+            if values.contains_key(solution) {
+                continue;
+            }
+            if expanded {
+                let args: Vec<T> = solution
+                    .args
+                    .0
+                    .iter()
+                    .map(|arg| values[&arg.solution])
+                    .collect();
+                values.insert(solution, combine(solution, &args));
+            } else {
+                pending.push((solution, true));
+                pending.extend(solution.args.0.iter().map(|arg| (&arg.solution, false)));
+            }
+        }
+        values[self]
+    }
+
+    /// Walk every distinct node once, in the order of a recursive depth first walk. `enter` is
+    /// called before the dependencies of a node and returns the ones to walk, `exit` after them.
+    /// The walk is iterative so deep trees do not exhaust the stack.
+    fn post_order<'a>(
+        &'a self,
+        mut enter: impl FnMut(&'a Solution) -> PyResult<Vec<&'a Solution>>,
+        mut exit: impl FnMut(&'a Solution) -> PyResult<()>,
+    ) -> PyResult<()> {
+        let mut done: HashSet<&'a Solution> = HashSet::new();
+        let mut pending = vec![(self, false)];
+        while let Some((solution, entered)) = pending.pop() {
+            if done.contains(solution) {
+                continue;
+            }
+            if entered {
+                done.insert(solution);
+                exit(solution)?;
+            } else {
+                pending.push((solution, true));
+                pending.extend(enter(solution)?.into_iter().rev().map(|dep| (dep, false)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` the output is fixed across processes and releases.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    /// Length prefixed so adjacent fields cannot run into each other.
+    fn write_field(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 impl Display for Solution {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.args.is_empty() {
@@ -1,16 +1,19 @@
 use std::fmt::Display;
 
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 
 #[pyclass(hash, eq, eq_int, frozen, module = "composify.core.solutions")]
-#[derive(PartialEq, Clone, Debug, Hash)]
+#[derive(PartialEq, Clone, Debug, Hash, Default)]
 pub enum SolveCardinality {
     /// Solve for all possible solutions.
     Exhaustive,
     /// Solve for a the first available solution.
     Single,
     /// Solve for an exclusive solution, raise error if multiple solutions are found.
+    #[default]
     Exclusive,
+    /// Solve for zero or one solution, raise error if multiple solutions are found.
+    AtMostOne,
 }
 
 #[pymethods]
@@ -20,6 +23,7 @@ impl SolveCardinality {
             Self::Exhaustive => "Exhaustive",
             Self::Single => "Single",
             Self::Exclusive => "Exclusive",
+            Self::AtMostOne => "AtMostOne",
         }
     }
 
@@ -34,35 +38,34 @@ impl SolveCardinality {
             Self::Exhaustive => '*',
             Self::Single => '1',
             Self::Exclusive => 'x',
+            Self::AtMostOne => '?',
         }
     }
 }
 
-impl Default for SolveCardinality {
-    fn default() -> Self {
-        Self::Exclusive
-    }
-}
-
 impl Display for SolveCardinality {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Exhaustive => write!(f, "Exhaustive"),
             Self::Single => write!(f, "Single"),
             Self::Exclusive => write!(f, "Exclusive"),
+            Self::AtMostOne => write!(f, "AtMostOne"),
         }
     }
 }
 
 #[pyclass(hash, eq, eq_int, frozen, module = "composify.core.solutions")]
-#[derive(PartialEq, Clone, Debug, Hash)]
+#[derive(PartialEq, Clone, Debug, Hash, Default)]
 pub enum SolveSpecificity {
     /// Solve for exact type.
     Exact,
     /// Solve allowing subclass.
+    #[default]
     AllowSubclass,
     /// Solve allowing superclass.
     AllowSuperclass,
+    /// Solve allowing either subclass or superclass.
+    AllowSubclassOrSuperclass,
 }
 
 #[pymethods]
@@ -72,6 +75,7 @@ impl SolveSpecificity {
             Self::Exact => "Exact",
             Self::AllowSubclass => "AllowSubclass",
             Self::AllowSuperclass => "AllowSuperclass",
+            Self::AllowSubclassOrSuperclass => "AllowSubclassOrSuperclass",
         }
     }
 
@@ -86,40 +90,122 @@ impl SolveSpecificity {
             Self::Exact => '=',
             Self::AllowSubclass => '+',
             Self::AllowSuperclass => '-',
+            Self::AllowSubclassOrSuperclass => '~',
         }
     }
 }
 
-impl Default for SolveSpecificity {
-    fn default() -> Self {
-        Self::AllowSubclass
-    }
-}
-
 impl Display for SolveSpecificity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Exact => write!(f, "Exact"),
             Self::AllowSubclass => write!(f, "AllowSubclass"),
             Self::AllowSuperclass => write!(f, "AllowSuperclass"),
+            Self::AllowSubclassOrSuperclass => write!(f, "AllowSubclassOrSuperclass"),
+        }
+    }
+}
+
+/// Markers changing how a dependency is bound, used as `Annotated` metadata.
+#[pyclass(hash, eq, eq_int, frozen, module = "composify.core.solutions")]
+#[derive(PartialEq, Clone, Debug, Hash)]
+pub enum DependencyMarker {
+    /// Bind every solution of the type as a single list argument.
+    Variadic,
+    /// Leave the argument out if the type cannot be resolved.
+    Optional,
+}
+
+#[pymethods]
+impl DependencyMarker {
+    pub fn __repr__(&self) -> &str {
+        match self {
+            Self::Variadic => "Variadic",
+            Self::Optional => "Optional",
         }
     }
 }
 
+/// Keep the first n solutions in priority order, used as `Annotated` metadata.
+#[pyclass(get_all, frozen, eq, hash, module = "composify.core.solutions")]
+#[derive(PartialEq, Clone, Debug, Hash)]
+pub struct First {
+    pub n: usize,
+}
+
+#[pymethods]
+impl First {
+    #[new]
+    pub fn __new__(n: usize) -> PyResult<Self> {
+        if n == 0 {
+            return Err(PyValueError::new_err(
+                "First requires at least one solution",
+            ));
+        }
+        Ok(Self { n })
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("First({})", self.n)
+    }
+}
+
+/// Solve for the highest priority solution, but raise if another solution has the same
+/// priority, used as `Annotated` metadata. Unlike `Single`, preferred attributes do not
+/// outrank priority.
+#[pyclass(frozen, eq, hash, module = "composify.core.solutions")]
+#[derive(PartialEq, Clone, Debug, Hash)]
+pub struct Strict;
+
+#[pymethods]
+impl Strict {
+    #[new]
+    pub fn __new__() -> Self {
+        Self
+    }
+
+    pub fn __repr__(&self) -> &str {
+        "Strict()"
+    }
+}
+
 #[pyclass(get_all, frozen, eq, module = "composify.core.solutions")]
-#[derive(PartialEq, Default, Clone, Debug)]
+#[derive(PartialEq, Default, Clone, Debug, Hash)]
 pub struct SolveParameter {
     pub specificity: SolveSpecificity,
     pub cardinality: SolveCardinality,
+    /// Maximum number of solutions kept under `Exhaustive` cardinality, set by `First`.
+    pub limit: Option<usize>,
+    /// Under `Single` cardinality, a tie between the two highest priorities is an error, set by
+    /// `Strict`.
+    pub strict: bool,
 }
 
 #[pymethods]
 impl SolveParameter {
     #[new]
-    pub fn __new__(specificity: &SolveSpecificity, cardinality: &SolveCardinality) -> Self {
+    #[pyo3(signature = (specificity, cardinality, limit=None, strict=false))]
+    pub fn __new__(
+        specificity: &SolveSpecificity,
+        cardinality: &SolveCardinality,
+        limit: Option<usize>,
+        strict: bool,
+    ) -> Self {
         Self {
             specificity: specificity.clone(),
             cardinality: cardinality.clone(),
+            limit,
+            strict,
+        }
+    }
+}
+
+impl SolveParameter {
+    /// The symbol of the cardinality, or the limit if there is one.
+    pub fn cardinality_symbol(&self) -> String {
+        match self.limit {
+            Some(limit) => limit.to_string(),
+            None => self.cardinality.symbol().to_string(),
         }
     }
 }
@@ -128,8 +214,15 @@ impl Display for SolveParameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Solve(specificity={}, cardinality={})",
+            "Solve(specificity={}, cardinality={}",
             self.specificity, self.cardinality
-        )
+        )?;
+        if let Some(limit) = self.limit {
+            write!(f, ", limit={}", limit)?;
+        }
+        if self.strict {
+            write!(f, ", strict")?;
+        }
+        write!(f, ")")
     }
 }
@@ -1,17 +1,32 @@
 use std::{
-    cell::{Ref, RefCell},
-    collections::HashMap,
+    cell::{Cell, Ref, RefCell},
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     rc::Rc,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
 };
 
-use pyo3::{create_exception, exceptions::PyException, prelude::*, types::PyTuple};
+use rayon::prelude::*;
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyBaseException, PyException, PyRuntimeError, PyTypeError, PyValueError},
+    prelude::*,
+    sync::MutexExt,
+    types::{PyDict, PyList, PyMapping, PyTuple, PyType},
+};
 
 use crate::{
     errors,
-    registry::RuleRegistry,
+    metadata::{AwaitedQualifiers, MetadataSet},
+    registry::{QualifierRejection, RuleRegistry, TypeHash},
+    rules::{Dependencies, Dependency, Rule},
     solutions::{Solution, SolutionArg, SolutionArgsCollection},
-    solve_parameters::SolveCardinality,
+    solve_parameters::{SolveCardinality, SolveParameter, SolveSpecificity},
     type_info::TypeInfo,
 };
 
@@ -19,22 +34,48 @@ use crate::{
 struct ExecutionStep<'a> {
     name: &'a str,
     target: &'a TypeInfo,
+    /// The rule being tried for the target.
+    rule: Option<&'a Rule>,
 }
 
 type ExecutionStack<'a> = Vec<ExecutionStep<'a>>;
 
-struct StepRaii<'a>(Rc<RefCell<ExecutionStack<'a>>>);
+/// Callback notified of every step pushed to and popped from the execution stack.
+type TraceHook<'a> = Option<(Python<'a>, &'a Py<PyAny>)>;
+
+struct StepRaii<'a> {
+    stack: Rc<RefCell<ExecutionStack<'a>>>,
+    hook: TraceHook<'a>,
+}
 
 impl<'a> StepRaii<'a> {
-    fn new(step: ExecutionStep<'a>, stack: Rc<RefCell<ExecutionStack<'a>>>) -> Self {
+    fn new(
+        step: ExecutionStep<'a>,
+        stack: Rc<RefCell<ExecutionStack<'a>>>,
+        hook: TraceHook<'a>,
+    ) -> Self {
+        if let Some((py, hook)) = hook {
+            call_trace_hook(py, hook, "push", &step);
+        }
         stack.borrow_mut().push(step);
-        Self(stack)
+        Self { stack, hook }
     }
 }
 
 impl Drop for StepRaii<'_> {
     fn drop(&mut self) {
-        self.0.borrow_mut().pop();
+        let step = self.stack.borrow_mut().pop();
+        if let (Some((py, hook)), Some(step)) = (self.hook, step) {
+            call_trace_hook(py, hook, "pop", &step);
+        }
+    }
+}
+
+/// Invoked without holding any borrow of the execution stack, so the hook may solve again.
+/// Errors cannot be propagated from a drop and are reported as unraisable instead.
+fn call_trace_hook(py: Python, hook: &Py<PyAny>, event: &str, step: &ExecutionStep) {
+    if let Err(err) = hook.call1(py, (event, step.name, step.target.clone())) {
+        err.write_unraisable(py, Some(hook.bind(py)));
     }
 }
 
@@ -42,74 +83,570 @@ fn clone_stack<'a>(stack: Ref<ExecutionStack<'a>>) -> ExecutionStack<'a> {
     stack.iter().cloned().collect()
 }
 
+struct MemoEntry {
+    solutions: Vec<Solution>,
+    /// Tick of the last read or write, the smallest is evicted first.
+    last_used: AtomicU64,
+}
+
 #[derive(Clone, Default)]
-pub struct SolutionsMemo(Arc<RwLock<HashMap<TypeInfo, Vec<Solution>>>>);
+pub struct SolutionsMemo {
+    entries: Arc<Mutex<HashMap<TypeInfo, MemoEntry>>>,
+    clock: Arc<AtomicU64>,
+    /// Maximum number of entries, the least recently used are evicted beyond it.
+    capacity: Option<usize>,
+}
 
 impl SolutionsMemo {
-    pub fn read_memo(&self, t: &TypeInfo) -> Option<Vec<Solution>> {
-        match self.0.read() {
-            Ok(map) => map.get(t).cloned(),
-            Err(_) => None,
+    /// Zero or None capacity means unbounded.
+    pub fn new(capacity: Option<usize>) -> Self {
+        Self {
+            capacity: capacity.filter(|capacity| *capacity > 0),
+            ..Default::default()
         }
     }
 
-    pub fn save_memo(&self, t: &TypeInfo, solutions: Vec<Solution>) {
-        if let Ok(mut map) = self.0.write() {
-            map.insert(t.clone(), solutions);
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Looking up entries compares metadata in Python, which may switch threads. The GIL is
+    /// released while waiting for the lock so the holder can finish. A poisoned memo may be
+    /// missing an update, so it is emptied rather than trusted.
+    fn entries(&self, py: Python) -> MutexGuard<'_, HashMap<TypeInfo, MemoEntry>> {
+        self.entries
+            .lock_py_attached(py)
+            .unwrap_or_else(|poisoned| {
+                let mut entries = poisoned.into_inner();
+                entries.clear();
+                self.entries.clear_poison();
+                entries
+            })
+    }
+
+    pub fn read_memo(&self, py: Python, t: &TypeInfo) -> Option<Vec<Solution>> {
+        self.entries(py).get(t).map(|entry| {
+            entry.last_used.store(self.tick(), Ordering::Relaxed);
+            entry.solutions.clone()
+        })
+    }
+
+    pub fn contains(&self, py: Python, t: &TypeInfo) -> bool {
+        self.entries(py).contains_key(t)
+    }
+
+    pub fn save_memo(&self, py: Python, t: &TypeInfo, solutions: Vec<Solution>) {
+        let mut map = self.entries(py);
+        map.insert(
+            t.clone(),
+            MemoEntry {
+                solutions,
+                last_used: AtomicU64::new(self.tick()),
+            },
+        );
+        if let Some(capacity) = self.capacity {
+            while map.len() > capacity {
+                let Some(oldest) = map
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                    .map(|(t, _)| t.clone())
+                else {
+                    break;
+                };
+                map.remove(&oldest);
+            }
         }
     }
+
+    /// Evict the entries of the type under any metadata or solve parameter, and of every type
+    /// whose rules depend on it through the registry, whether the memoized solutions used it
+    /// or were computed while it was missing. Entries whose solutions contain it are evicted
+    /// too.
+    pub fn invalidate(&self, py: Python, t: &TypeInfo, registry: &RuleRegistry) {
+        let dependents = registry.dependent_types(t.type_hash);
+        let affected = |type_hash: TypeHash| {
+            dependents.iter().any(|dependent| {
+                *dependent == type_hash
+                    || registry
+                        .specificity_distance(type_hash, *dependent)
+                        .is_some()
+            })
+        };
+        let related = |solution: &Solution| affected(solution.rule.output_type.type_hash);
+        let mut map = self.entries(py);
+        map.retain(|key, entry| {
+            let requested = key.element.as_deref().unwrap_or(key).type_hash;
+            !affected(key.type_hash)
+                && !affected(requested)
+                && !entry.solutions.iter().any(|s| s.any_node(related))
+        });
+    }
+
+    pub fn clear(&self, py: Python) {
+        self.entries(py).clear();
+    }
+
+    /// The number of memoized solutions of each type.
+    pub fn solution_counts(&self, py: Python) -> Vec<(TypeInfo, usize)> {
+        self.entries(py)
+            .iter()
+            .map(|(t, entry)| (t.clone(), entry.solutions.len()))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub enum SolvingErrorReason {
-    CyclicDependency,
-    NoSolution,
+    /// Index of the step where the cycle starts, the last step repeats its target.
+    CyclicDependency(usize),
+    MaxDepthExceeded,
+    /// Includes the rules rejected by the qualifiers of the target.
+    NoSolution(Vec<QualifierRejection>),
     NotExclusive(Vec<Solution>),
+    /// A strict single target had several solutions sharing the highest priority.
+    AmbiguousSingle(Vec<Solution>),
+    /// The number of argument permutations of a rule exceeded the solver's limit.
+    TooManyPermutations(usize),
+    /// An exhaustive target had more solutions than the solver's limit, which is included.
+    TooManySolutions(usize),
+    /// The solve ran out of time or node visits.
+    Timeout,
 }
 
 create_exception!(composify.core.solver, SolvingError, PyException);
 
-fn permutate_candidates(
+/// Lazily expand the candidate solutions of each dependency into every argument combination,
+/// in the order of the candidates with the last dependency varying fastest.
+///
+/// Consumed with the GIL released, so it must not call into Python.
+struct Permutations {
     candidates: Vec<SolutionArgCandidate>,
-) -> Result<Vec<SolutionArgsCollection>, SolvingErrorReason> {
-    let mut curr_iteration: Vec<SolutionArgsCollection>;
-    let mut next_iteration: Vec<SolutionArgsCollection> = Vec::new();
-    let mut iter = candidates.into_iter();
-    if let Some(c) = iter.next() {
-        for s in c.solutions {
-            next_iteration.push(SolutionArgsCollection::new(vec![SolutionArg {
+    /// The candidate solution of each dependency in the next combination, None once exhausted.
+    indices: Option<Vec<usize>>,
+}
+
+impl Iterator for Permutations {
+    type Item = SolutionArgsCollection;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let indices = self.indices.as_mut()?;
+        let args = self
+            .candidates
+            .iter()
+            .zip(indices.iter())
+            .map(|(c, &index)| SolutionArg {
                 name: c.name.clone(),
-                solution: s,
-            }]));
-        }
-    } else {
-        return Err(SolvingErrorReason::NoSolution);
-    }
-
-    for c in iter {
-        curr_iteration = Vec::new();
-        curr_iteration.extend(next_iteration);
-        next_iteration = Vec::new();
-        for args in curr_iteration.into_iter() {
-            for solution in c.solutions.iter() {
-                let mut args = args.clone();
-                args.0.push(SolutionArg {
-                    name: c.name.to_string(),
-                    solution: solution.clone(),
-                });
-                next_iteration.push(args)
+                solution: c.solutions[index].clone(),
+            })
+            .collect();
+        let mut exhausted = true;
+        for (c, index) in self.candidates.iter().zip(indices.iter_mut()).rev() {
+            *index += 1;
+            if *index < c.solutions.len() {
+                exhausted = false;
+                break;
+            }
+            *index = 0;
+        }
+        if exhausted {
+            self.indices = None;
+        }
+        Some(SolutionArgsCollection::new(args))
+    }
+}
+
+/// Check the number of argument combinations of the candidates against the limit before
+/// expanding them.
+fn permutate_candidates(
+    candidates: Vec<SolutionArgCandidate>,
+    max_permutations: Option<usize>,
+) -> Result<Permutations, SolvingErrorReason> {
+    let count = candidates
+        .iter()
+        .try_fold(1usize, |count, c| count.checked_mul(c.solutions.len()))
+        .unwrap_or(usize::MAX);
+    if max_permutations.is_some_and(|max_permutations| count > max_permutations) {
+        return Err(SolvingErrorReason::TooManyPermutations(count));
+    }
+    // Every dependency left out as optional still makes a single empty combination.
+    let indices = (count > 0).then(|| vec![0; candidates.len()]);
+    Ok(Permutations {
+        candidates,
+        indices,
+    })
+}
+
+/// Remove repeated solutions, such as the same rule inherited through several registries,
+/// keeping the first occurrence.
+fn dedupe_solutions(solutions: Vec<Solution>) -> Vec<Solution> {
+    let mut seen = HashSet::with_capacity(solutions.len());
+    solutions
+        .into_iter()
+        .filter(|solution| seen.insert(solution.clone()))
+        .collect()
+}
+
+/// Which rule variants are selected when a type has both sync and async rules.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExecutionMode {
+    Sync,
+    Async,
+}
+
+impl ExecutionMode {
+    fn accepts(&self, rule: &Rule) -> bool {
+        rule.is_async == (*self == ExecutionMode::Async)
+    }
+}
+
+/// For each output type, keep only the rules matching the execution mode if there are any.
+/// Otherwise the rules of the other mode are kept as a bridge.
+fn select_execution_variants(rules: Vec<&Rule>, mode: ExecutionMode) -> Vec<&Rule> {
+    let accepted: HashSet<&TypeInfo> = rules
+        .iter()
+        .filter(|rule| mode.accepts(rule))
+        .map(|rule| &rule.output_type)
+        .collect();
+    rules
+        .into_iter()
+        .filter(|rule| mode.accepts(rule) || !accepted.contains(&rule.output_type))
+        .collect()
+}
+
+/// Callable returning a prebuilt value, used to mock dependencies.
+#[pyclass(frozen, module = "composify.core.solver")]
+pub struct MockValue {
+    value: Py<PyAny>,
+}
+
+#[pymethods]
+impl MockValue {
+    fn __call__(&self, py: Python) -> Py<PyAny> {
+        self.value.clone_ref(py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("MockValue({})", self.value)
+    }
+}
+
+pub const MOCK_RULE_NAME: &str = "__mock__";
+
+/// Callable building a collection from its `item_<index>` keyword arguments.
+#[pyclass(frozen, module = "composify.core.solver")]
+pub struct CollectValues {
+    container: Py<PyType>,
+}
+
+#[pymethods]
+impl CollectValues {
+    #[pyo3(signature = (**items))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        items: Option<Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let mut values = Vec::new();
+        if let Some(items) = items {
+            for (name, value) in items.iter() {
+                let name = name.extract::<String>()?;
+                let index = name
+                    .strip_prefix(COLLECT_ITEM_PREFIX)
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .ok_or_else(|| PyTypeError::new_err(format!("Unexpected item {name}")))?;
+                values.push((index, value));
             }
         }
+        values.sort_by_key(|(index, _)| *index);
+        let values = PyList::new(py, values.into_iter().map(|(_, value)| value))?;
+        self.container.bind(py).call1((values,))
     }
 
-    Ok(next_iteration)
+    fn __repr__(&self) -> String {
+        format!("CollectValues({})", self.container)
+    }
+}
+
+pub const COLLECT_RULE_NAME: &str = "__collect__";
+const COLLECT_ITEM_PREFIX: &str = "item_";
+
+/// Replacement for a type that skips the registry.
+enum Mock {
+    /// Used as the only solution.
+    Solution(Solution),
+    /// Used as the only rule, its dependencies are still solved. Only the first solution is
+    /// kept, like a mocked object the target always has exactly one.
+    Rule(Rule),
+}
+
+fn parse_mocks(mocks: &Bound<PyMapping>) -> PyResult<HashMap<TypeHash, Mock>> {
+    let py = mocks.py();
+    let mut result = HashMap::new();
+    for item in mocks.items()?.iter() {
+        let typing = TypeInfo::parse(item.get_item(0)?)?;
+        let value = item.get_item(1)?;
+        let mock = if let Ok(solution) = value.downcast::<Solution>() {
+            Mock::Solution(solution.get().clone())
+        } else if let Ok(rule) = value.downcast::<Rule>() {
+            Mock::Rule(rule.get().clone())
+        } else {
+            Mock::Rule(Rule {
+                function: Arc::new(
+                    Py::new(
+                        py,
+                        MockValue {
+                            value: value.unbind(),
+                        },
+                    )?
+                    .into_any(),
+                ),
+                canonical_name: MOCK_RULE_NAME.to_string(),
+                output_type: typing.clone(),
+                dependencies: Dependencies::default(),
+                priority: 0,
+                is_async: false,
+                guard: None,
+                tags: Default::default(),
+                enabled: None,
+                module: None,
+                qualname: None,
+                lineno: None,
+            })
+        };
+        result.insert(typing.type_hash, mock);
+    }
+    Ok(result)
 }
 
 pub struct _Solver<'a> {
     solver: &'a Solver,
     py: Python<'a>,
+    mode: ExecutionMode,
     execution_stack: Rc<RefCell<ExecutionStack<'a>>>,
     errors: RefCell<Vec<(ExecutionStack<'a>, SolvingErrorReason)>>,
+    /// Set when the solutions being built depend on the execution stack and must not be memoized.
+    stack_dependent: Cell<bool>,
+    /// Rules providing these attributes are ranked first.
+    prefer_attributes: MetadataSet,
+    /// Dependencies deeper than this are left as unresolved placeholders.
+    max_preview_depth: Option<usize>,
+    /// Types resolved to the mocks instead of using the registry.
+    mocks: HashMap<TypeHash, Mock>,
+    /// Types found to have no solution. Kept for a single solve only, and failures that depend on
+    /// the execution stack (cycles, depth limit, guards) are never recorded.
+    failures: RefCell<HashSet<TypeInfo>>,
+    /// Only complete solutions of the root target accepted by this predicate are kept.
+    filter: Option<Py<PyAny>>,
+    stats: RefCell<Stats>,
+    /// Solving aborts once this instant has passed.
+    deadline: Option<Instant>,
+    /// Solving aborts once this many targets were entered.
+    max_visits: Option<usize>,
+    /// Targets entered, shared with the prefetching solvers so they draw from the same budget.
+    visits: Arc<AtomicUsize>,
+    /// Set once the deadline or visit budget is exceeded.
+    expired: Cell<bool>,
+    /// Prefetch sibling dependencies on the thread pool, disabled within the prefetching solvers.
+    parallel: bool,
+}
+
+/// Counters collected while solving, for performance debugging.
+#[pyclass(get_all, frozen, module = "composify.core.solver")]
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// Number of rules tried.
+    pub rules_evaluated: usize,
+    /// Number of targets answered by the memo.
+    pub memo_hits: usize,
+    /// Deepest execution stack reached.
+    pub max_depth: usize,
+    /// Number of argument permutations built.
+    pub permutations_generated: usize,
+}
+
+#[pymethods]
+impl Stats {
+    fn __repr__(&self) -> String {
+        format!(
+            "Stats(rules_evaluated={}, memo_hits={}, max_depth={}, permutations_generated={})",
+            self.rules_evaluated, self.memo_hits, self.max_depth, self.permutations_generated
+        )
+    }
+}
+
+/// A target that failed to solve, see `Solver.explain`.
+#[pyclass(get_all, frozen, module = "composify.core.solver")]
+pub struct Explanation {
+    pub name: String,
+    pub target: TypeInfo,
+    /// Errors raised for the target itself, such as having no rule or multiple solutions.
+    pub errors: Vec<Py<PyAny>>,
+    /// Rules tried for the target whose dependencies failed.
+    pub rules: Vec<Py<RuleAttempt>>,
+}
+
+/// A rule tried for a target and the dependencies that failed it.
+#[pyclass(get_all, frozen, module = "composify.core.solver")]
+pub struct RuleAttempt {
+    /// Canonical name of the rule, None for the elements of a collection.
+    pub rule: Option<String>,
+    pub dependencies: Vec<Py<Explanation>>,
+}
+
+#[pymethods]
+impl Explanation {
+    /// The failed targets without any failed rule, where the failures originate.
+    fn leaves(slf: &Bound<'_, Self>) -> Vec<Py<Explanation>> {
+        let py = slf.py();
+        let mut leaves = Vec::new();
+        let mut pending = vec![slf.clone().unbind()];
+        while let Some(node) = pending.pop() {
+            let explanation = node.get();
+            if explanation.rules.is_empty() {
+                leaves.push(node);
+                continue;
+            }
+            for attempt in explanation.rules.iter().rev() {
+                for dependency in attempt.get().dependencies.iter().rev() {
+                    pending.push(dependency.clone_ref(py));
+                }
+            }
+        }
+        leaves
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Explanation({}, target={}, errors={}, rules={})",
+            self.name,
+            self.target,
+            self.errors.len(),
+            self.rules.len()
+        )
+    }
+}
+
+#[pymethods]
+impl RuleAttempt {
+    fn __repr__(&self) -> String {
+        format!(
+            "RuleAttempt({}, dependencies={})",
+            self.rule.as_deref().unwrap_or("None"),
+            self.dependencies.len()
+        )
+    }
+}
+
+/// How solutions of an exhaustive target are ordered.
+#[pyclass(hash, eq, eq_int, frozen, module = "composify.core.solver")]
+#[derive(PartialEq, Clone, Copy, Debug, Hash, Default)]
+pub enum ResolutionOrder {
+    /// Closest output types first, then highest score.
+    #[default]
+    DepthFirst,
+    /// Shallowest solution trees first, then as `DepthFirst`.
+    BreadthFirst,
+}
+
+/// The types reachable from a target and their structural dependencies, see
+/// `Solver.dependency_graph`.
+#[pyclass(get_all, frozen, module = "composify.core.solver")]
+pub struct DependencyGraph {
+    /// Each reachable type with the dependency types of its rules, or the element of a
+    /// collection without rules of its own.
+    pub edges: HashMap<TypeInfo, Vec<TypeInfo>>,
+    /// Edges `(dependent, dependency)` leading back to a type being walked, closing a cycle.
+    pub cycles: Vec<(TypeInfo, TypeInfo)>,
+}
+
+#[pymethods]
+impl DependencyGraph {
+    fn __repr__(&self) -> String {
+        format!(
+            "DependencyGraph(types={}, cycles={})",
+            self.edges.len(),
+            self.cycles.len()
+        )
+    }
+}
+
+/// An `Explanation` being assembled from the execution stacks of the recorded errors.
+struct ExplanationNode<'s> {
+    name: &'s str,
+    target: &'s TypeInfo,
+    errors: Vec<PyErr>,
+    rules: Vec<(Option<&'s str>, Vec<ExplanationNode<'s>>)>,
+}
+
+impl<'s> ExplanationNode<'s> {
+    fn new(step: &ExecutionStep<'s>) -> Self {
+        Self {
+            name: step.name,
+            target: step.target,
+            errors: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Follow the stack below this node, creating the missing nodes, and file the error at its end.
+    fn insert(&mut self, stack: &[ExecutionStep<'s>], error: PyErr) {
+        let [step, rest @ ..] = stack else {
+            return;
+        };
+        let Some(next) = rest.first() else {
+            self.errors.push(error);
+            return;
+        };
+        let rule = step.rule.map(|rule| rule.canonical_name.as_str());
+        let attempt = match self.rules.iter().position(|(name, _)| *name == rule) {
+            Some(index) => &mut self.rules[index].1,
+            None => {
+                self.rules.push((rule, Vec::new()));
+                &mut self.rules.last_mut().expect("attempt was pushed").1
+            }
+        };
+        let child = match attempt
+            .iter()
+            .position(|child| child.name == next.name && child.target == next.target)
+        {
+            Some(index) => &mut attempt[index],
+            None => {
+                attempt.push(ExplanationNode::new(next));
+                attempt.last_mut().expect("child was pushed")
+            }
+        };
+        child.insert(rest, error);
+    }
+
+    fn build(self, py: Python) -> PyResult<Py<Explanation>> {
+        let mut rules = Vec::with_capacity(self.rules.len());
+        for (rule, dependencies) in self.rules {
+            let dependencies = dependencies
+                .into_iter()
+                .map(|dependency| dependency.build(py))
+                .collect::<PyResult<_>>()?;
+            rules.push(Py::new(
+                py,
+                RuleAttempt {
+                    rule: rule.map(str::to_string),
+                    dependencies,
+                },
+            )?);
+        }
+        Py::new(
+            py,
+            Explanation {
+                name: self.name.to_string(),
+                target: self.target.clone(),
+                errors: self
+                    .errors
+                    .into_iter()
+                    .map(|error| error.into_value(py).into_any())
+                    .collect(),
+                rules,
+            },
+        )
+    }
 }
 
 pub struct SolutionArgCandidate {
@@ -117,14 +654,142 @@ pub struct SolutionArgCandidate {
     pub solutions: Vec<Solution>,
 }
 
+/// A target whose rules are being tried.
+struct TargetFrame<'a> {
+    requested: &'a TypeInfo,
+    /// The requested target with the policy of the solver for its type applied, if any.
+    applied: Option<Box<TypeInfo>>,
+    /// Pops the target from the execution stack when the frame is dropped.
+    _step: StepRaii<'a>,
+    rules: std::vec::IntoIter<&'a Rule>,
+    /// Element of a collection target that is yet to be solved.
+    element: Option<&'a TypeInfo>,
+    /// Element of a collection target whose solutions are awaited.
+    collecting: Option<&'a TypeInfo>,
+    rule: Option<RuleFrame<'a>>,
+    solutions: Vec<Solution>,
+    /// Rules rejected by the qualifiers of the target, reported if it has no solution.
+    rejections: Vec<QualifierRejection>,
+    parent_stack_dependent: bool,
+}
+
+impl TargetFrame<'_> {
+    fn target(&self) -> &TypeInfo {
+        self.applied.as_deref().unwrap_or(self.requested)
+    }
+}
+
+/// A rule whose dependencies are being solved.
+struct RuleFrame<'a> {
+    rule: &'a Rule,
+    match_kind: Option<SolveSpecificity>,
+    dependencies: std::vec::IntoIter<&'a Dependency>,
+    /// The dependency whose solutions are awaited.
+    pending: Option<&'a Dependency>,
+    args: Vec<SolutionArgCandidate>,
+}
+
+enum Entry<'a> {
+    /// Answered without trying any rule.
+    Solved(Option<Vec<Solution>>),
+    Frame(TargetFrame<'a>),
+}
+
+enum Step<'a> {
+    /// The frame awaits the solutions of a dependency.
+    Solve(&'a str, &'a TypeInfo),
+    /// All rules of the frame were tried.
+    Done,
+}
+
 impl<'a> _Solver<'a> {
-    fn new(solver: &'a Solver, py: Python<'a>) -> Self {
+    fn new(solver: &'a Solver, py: Python<'a>, mode: ExecutionMode) -> Self {
         Self {
             solver,
             py,
+            mode,
             execution_stack: Rc::new(RefCell::new(Vec::new())),
             errors: RefCell::new(Vec::new()),
+            stack_dependent: Cell::new(false),
+            prefer_attributes: MetadataSet::default(),
+            max_preview_depth: None,
+            mocks: HashMap::new(),
+            failures: RefCell::new(HashSet::new()),
+            filter: None,
+            stats: RefCell::new(Stats::default()),
+            deadline: None,
+            max_visits: None,
+            visits: Arc::new(AtomicUsize::new(0)),
+            expired: Cell::new(false),
+            parallel: solver.parallel,
+        }
+    }
+
+    /// Preferences, previews, mocks, and filters only apply to the current solve, so they bypass
+    /// the shared memo.
+    fn uses_memo(&self) -> bool {
+        self.prefer_attributes.is_empty()
+            && self.max_preview_depth.is_none()
+            && self.mocks.is_empty()
+            && self.filter.is_none()
+    }
+
+    fn filter_solutions(&self, solutions: Vec<Solution>) -> PyResult<Vec<Solution>> {
+        let Some(filter) = &self.filter else {
+            return Ok(solutions);
+        };
+        let mut accepted = Vec::new();
+        for solution in solutions {
+            if filter
+                .call1(self.py, (solution.clone(),))?
+                .is_truthy(self.py)?
+            {
+                accepted.push(solution);
+            }
         }
+        Ok(accepted)
+    }
+
+    /// If dependencies of the current step are beyond the preview depth.
+    fn beyond_preview_depth(&self) -> bool {
+        self.max_preview_depth
+            .is_some_and(|depth| self.execution_stack.borrow().len() > depth)
+    }
+
+    /// Combine the recorded errors into a single failure.
+    fn failure(&self) -> PyResult<PyErr> {
+        let errors: PyResult<Vec<PyErr>> = self
+            .errors
+            .borrow()
+            .iter()
+            .map(|(s, r)| make_py_error(self.py, s, r))
+            .collect();
+        Ok(errors::SolveFailureError::new_err(errors?))
+    }
+
+    /// Count a visit, returns true once the budget of the solve is exceeded.
+    fn exceeds_budget(&self) -> bool {
+        if self.expired.get() {
+            return true;
+        }
+        let visits = self.visits.fetch_add(1, Ordering::Relaxed) + 1;
+        if self
+            .max_visits
+            .is_some_and(|max_visits| visits > max_visits)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.expire();
+            return true;
+        }
+        false
+    }
+
+    fn expire(&self) {
+        self.push_error(SolvingErrorReason::Timeout);
+        self.expired.set(true);
+        self.stack_dependent.set(true);
     }
 
     fn push_error(&self, error: SolvingErrorReason) {
@@ -133,104 +798,614 @@ impl<'a> _Solver<'a> {
             .push((clone_stack(self.execution_stack.borrow()), error));
     }
 
-    fn push_stack(&'a self, name: &'a str, target: &'a TypeInfo) -> Option<StepRaii> {
-        let step = ExecutionStep { name, target };
-        if self.execution_stack.borrow().len() > 5 {
-            println!("{}", step.target.type_hash);
-            self.execution_stack
-                .borrow()
-                .iter()
-                .map(|f| println!("{}", f.target == step.target))
-                .for_each(drop);
+    fn push_stack(&'a self, name: &'a str, target: &'a TypeInfo) -> Option<StepRaii<'a>> {
+        let step = ExecutionStep {
+            name,
+            target,
+            rule: None,
+        };
+        if self
+            .solver
+            .max_depth
+            .is_some_and(|max_depth| self.execution_stack.borrow().len() >= max_depth)
+        {
+            let _raii = StepRaii::new(step, self.execution_stack.clone(), self.trace_hook());
+            self.push_error(SolvingErrorReason::MaxDepthExceeded);
+            self.stack_dependent.set(true);
             return None;
         }
-        if self
+        let cycle_start = self
             .execution_stack
             .borrow()
             .iter()
-            .any(|f| f.target == step.target)
-        {
-            let _raii = StepRaii::new(step, self.execution_stack.clone());
-            self.push_error(SolvingErrorReason::CyclicDependency);
+            .position(|f| f.target == step.target);
+        if let Some(cycle_start) = cycle_start {
+            let _raii = StepRaii::new(step, self.execution_stack.clone(), self.trace_hook());
+            self.push_error(SolvingErrorReason::CyclicDependency(cycle_start));
+            self.stack_dependent.set(true);
             None
         } else {
-            Some(StepRaii::new(step, self.execution_stack.clone()))
+            let step = StepRaii::new(step, self.execution_stack.clone(), self.trace_hook());
+            let depth = self.execution_stack.borrow().len();
+            let mut stats = self.stats.borrow_mut();
+            stats.max_depth = stats.max_depth.max(depth);
+            Some(step)
         }
     }
 
-    fn solve_for<'b: 'a>(
-        &'b self,
-        name: &'b str,
-        target: &'b TypeInfo,
-    ) -> PyResult<Option<Vec<Solution>>> {
-        if let Some(solutions) = self.solver.memo.read_memo(target) {
-            return Ok(Some(solutions));
+    fn trace_hook(&self) -> TraceHook<'a> {
+        self.solver
+            .trace_hook
+            .as_deref()
+            .map(|hook| (self.py, hook))
+    }
+
+    /// Record the rule being tried for the target on top of the execution stack.
+    fn trace_rule(&self, rule: &'a Rule) {
+        if let Some(step) = self.execution_stack.borrow_mut().last_mut() {
+            step.rule = Some(rule);
         }
-        // If unnamed (_), value is immediately dropped.
-        let _pop_on_drop = self.push_stack(name, target);
-        if _pop_on_drop.is_none() {
-            return Ok(None);
+    }
+
+    fn resolution_path(&self) -> Vec<TypeInfo> {
+        self.execution_stack
+            .borrow()
+            .iter()
+            .map(|step| step.target.clone())
+            .collect()
+    }
+
+    /// Solve the sibling dependencies of a rule concurrently, each in a separate solver with its own
+    /// execution stack. Solutions independent of the stack are saved to the shared memo, where
+    /// the sequential walk picks them up. Anything else, including errors, is solved again in
+    /// place, so the results match solving without prefetching.
+    ///
+    /// The forks share the deadline and visit budget, and their stats are added to this solver's.
+    /// Prefetching is skipped when a trace hook is set, as the hook is only ever called from the
+    /// thread solving.
+    fn prefetch(&self, targets: Vec<&TypeInfo>) {
+        if self.solver.trace_hook.is_some() {
+            return;
+        }
+        let targets: Vec<&TypeInfo> = targets
+            .into_iter()
+            .filter(|target| !self.memo().contains(self.py, target))
+            .collect();
+        if targets.len() < 2 {
+            return;
+        }
+        let solver = self.solver;
+        let mode = self.mode;
+        let deadline = self.deadline;
+        let max_visits = self.max_visits;
+        let visits = &self.visits;
+        let forks_stats: Vec<Stats> = self.py.allow_threads(|| {
+            targets
+                .into_par_iter()
+                .map(|target| {
+                    Python::with_gil(|py| {
+                        let mut fork = _Solver::new(solver, py, mode);
+                        fork.deadline = deadline;
+                        fork.max_visits = max_visits;
+                        fork.visits = visits.clone();
+                        fork.parallel = false;
+                        // Failures are reported by the sequential walk.
+                        let _ = fork.solve_for("__root__", target);
+                        fork.stats.take()
+                    })
+                })
+                .collect()
+        });
+        let depth = self.execution_stack.borrow().len();
+        let mut stats = self.stats.borrow_mut();
+        for fork_stats in forks_stats {
+            stats.rules_evaluated += fork_stats.rules_evaluated;
+            stats.memo_hits += fork_stats.memo_hits;
+            stats.max_depth = stats.max_depth.max(depth + fork_stats.max_depth);
+            stats.permutations_generated += fork_stats.permutations_generated;
+        }
+    }
+
+    /// Estimate the number of candidates for a target without solving it.
+    fn estimate_candidates(&self, target: &TypeInfo) -> usize {
+        if self.mocks.contains_key(&target.type_hash) {
+            return 1;
         }
-        let rules = if let Some(rules) = self.solver.rules.get(self.py, target)? {
-            rules
+        if self.failures.borrow().contains(target) {
+            return 0;
+        }
+        if let Some(solutions) = self.read_memo(target) {
+            return solutions.len();
+        }
+        self.solver.rules.count_candidates(target)
+    }
+
+    fn memo(&self) -> &SolutionsMemo {
+        match self.mode {
+            ExecutionMode::Sync => &self.solver.memo,
+            ExecutionMode::Async => &self.solver.async_memo,
+        }
+    }
+
+    fn read_memo(&self, target: &TypeInfo) -> Option<Vec<Solution>> {
+        if self.uses_memo() {
+            self.memo().read_memo(self.py, target)
         } else {
-            self.push_error(SolvingErrorReason::NoSolution);
-            return Ok(None);
+            None
+        }
+    }
+
+    /// Solve a target using an explicit stack of frames, so the depth of the dependency graph
+    /// is bounded by the heap rather than the native call stack.
+    fn solve_for(&'a self, name: &'a str, target: &'a TypeInfo) -> PyResult<Option<Vec<Solution>>> {
+        let mut frames = match self.enter(name, target)? {
+            Entry::Solved(solutions) => {
+                return solutions
+                    .map(|solutions| self.filter_solutions(solutions))
+                    .transpose();
+            }
+            Entry::Frame(frame) => vec![frame],
         };
-        let mut solutions = Vec::new();
-        'rule: for rule in rules {
-            if rule.dependencies.is_empty() {
-                solutions.push(Solution {
-                    rule: rule.clone(),
-                    args: SolutionArgsCollection::default(),
-                });
-            } else {
-                let mut args: Vec<SolutionArgCandidate> = Vec::new();
-                for dependency in rule.dependencies.iter() {
-                    match self.solve_for(dependency.name.as_str(), &dependency.typing)? {
-                        Some(solutions) => {
-                            args.push(SolutionArgCandidate {
+        let mut solved = None;
+        while let Some(frame) = frames.last_mut() {
+            if self.expired.get() {
+                return Ok(None);
+            }
+            match self.advance(frame, solved.take())? {
+                Step::Solve(name, target) => match self.enter(name, target)? {
+                    Entry::Solved(solutions) => solved = Some(solutions),
+                    Entry::Frame(frame) => frames.push(frame),
+                },
+                Step::Done => {
+                    let mut frame = frames.pop().expect("advanced frame exists");
+                    if frames.is_empty() {
+                        frame.solutions = self.filter_solutions(frame.solutions)?;
+                    }
+                    solved = Some(self.finish(frame)?);
+                }
+            }
+        }
+        Ok(solved.flatten())
+    }
+
+    /// The target with its solve parameter replaced by the policy of the solver for its type.
+    fn apply_policy(&self, target: &TypeInfo) -> Option<Box<TypeInfo>> {
+        let policy = self.solver.policies.get(&target.type_hash)?;
+        (*policy != target.solve_parameter)
+            .then(|| Box::new(target.with_solve_parameter(policy.clone())))
+    }
+
+    /// Start solving a target, unless it can be answered without trying its rules.
+    fn enter(&'a self, name: &'a str, requested: &'a TypeInfo) -> PyResult<Entry<'a>> {
+        let applied = self.apply_policy(requested);
+        let target = applied.as_deref().unwrap_or(requested);
+        if let Some(solutions) = self.read_memo(target) {
+            self.stats.borrow_mut().memo_hits += 1;
+            return Ok(Entry::Solved(Some(solutions)));
+        }
+        if self.failures.borrow().contains(target) {
+            return Ok(Entry::Solved(self.empty_solutions(target)));
+        }
+        let Some(step) = self.push_stack(name, requested) else {
+            return Ok(Entry::Solved(None));
+        };
+        if self.exceeds_budget() {
+            return Ok(Entry::Solved(None));
+        }
+        let (rules, rejections) = if let Some(mock) = self.mocks.get(&target.type_hash) {
+            match mock {
+                Mock::Solution(solution) => return Ok(Entry::Solved(Some(vec![solution.clone()]))),
+                Mock::Rule(rule) => (vec![rule], Vec::new()),
+            }
+        } else if target.element.is_some() {
+            (Vec::new(), Vec::new())
+        } else {
+            match self
+                .solver
+                .rules
+                .get_explained(self.py, target, &self.prefer_attributes)?
+            {
+                (Some(rules), rejections) => (rules, rejections),
+                (None, rejections) => {
+                    self.failures.borrow_mut().insert(target.clone());
+                    return Ok(Entry::Solved(self.no_solution(target, rejections)));
+                }
+            }
+        };
+        let rules = select_execution_variants(rules, self.mode);
+        let element = match &requested.element {
+            Some(element) if rules.is_empty() => Some(element.as_ref()),
+            _ => None,
+        };
+        Ok(Entry::Frame(TargetFrame {
+            requested,
+            applied,
+            _step: step,
+            rules: rules.into_iter(),
+            element,
+            collecting: None,
+            rule: None,
+            solutions: Vec::new(),
+            rejections,
+            parent_stack_dependent: self.stack_dependent.replace(false),
+        }))
+    }
+
+    /// Try the rules of a frame until a dependency has to be solved or no rules are left.
+    /// `solved` carries the solutions of the dependency requested by the previous step.
+    fn advance(
+        &'a self,
+        frame: &mut TargetFrame<'a>,
+        mut solved: Option<Option<Vec<Solution>>>,
+    ) -> PyResult<Step<'a>> {
+        if let Some(element) = frame.collecting.take() {
+            if let Some(items) = solved.take().flatten() {
+                frame
+                    .solutions
+                    .push(self.collect_solution(frame.target(), element, items)?);
+            }
+        }
+        if let Some(element) = frame.element.take() {
+            frame.collecting = Some(element);
+            return Ok(Step::Solve("item", element));
+        }
+        loop {
+            if let Some(current) = frame.rule.as_mut() {
+                if let Some(dependency) = current.pending.take() {
+                    match solved.take().flatten() {
+                        Some(solutions) if dependency.variadic => {
+                            let list = self.variadic_list(&dependency.typing)?;
+                            current.args.push(SolutionArgCandidate {
+                                name: dependency.name.to_string(),
+                                solutions: vec![self.collect_solution(
+                                    &list,
+                                    &dependency.typing,
+                                    solutions,
+                                )?],
+                            })
+                        }
+                        Some(solutions) if !(solutions.is_empty() && dependency.optional) => {
+                            current.args.push(SolutionArgCandidate {
                                 name: dependency.name.to_string(),
                                 solutions,
-                            });
+                            })
+                        }
+                        // Unresolved optional dependencies are left out of the arguments.
+                        _ if dependency.optional => {}
+                        _ => {
+                            frame.rule = None;
+                            continue;
                         }
-                        None => continue 'rule,
                     }
                 }
-                match permutate_candidates(args) {
+                if let Some(dependency) = current.dependencies.next() {
+                    current.pending = Some(dependency);
+                    return Ok(Step::Solve(dependency.name.as_str(), &dependency.typing));
+                }
+                let current = frame.rule.take().expect("rule in progress");
+                let (max_permutations, deadline) = (self.solver.max_permutations, self.deadline);
+                let needed = self
+                    .permutations_needed(frame.target(), frame.solutions.len())
+                    .unwrap_or(usize::MAX);
+                // Permutating only shuffles Rust handles, other threads may run Python meanwhile.
+                match self.py.allow_threads(move || {
+                    let mut args = Vec::new();
+                    for permutation in
+                        permutate_candidates(current.args, max_permutations)?.take(needed)
+                    {
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            return Err(SolvingErrorReason::Timeout);
+                        }
+                        args.push(permutation);
+                    }
+                    Ok(args)
+                }) {
                     Ok(args) => {
+                        self.stats.borrow_mut().permutations_generated += args.len();
                         for args in args {
-                            solutions.push(Solution {
-                                rule: rule.clone(),
+                            frame.solutions.push(Solution {
+                                rule: current.rule.clone(),
                                 args,
+                                match_kind: current.match_kind.clone(),
+                                is_unresolved: false,
                             });
                         }
                     }
+                    Err(SolvingErrorReason::Timeout) => self.expire(),
                     Err(e) => self.push_error(e),
                 }
+                continue;
+            }
+            if self.exceeds_max_solutions(frame.target(), frame.solutions.len()) {
+                // Enough solutions to truncate or to report the overflow.
+                return Ok(Step::Done);
+            }
+            let Some(rule) = frame.rules.next() else {
+                return Ok(Step::Done);
+            };
+            self.stats.borrow_mut().rules_evaluated += 1;
+            self.trace_rule(rule);
+            if rule.guard.is_some() {
+                self.stack_dependent.set(true);
+                if !rule.check_guard(self.py, self.resolution_path())? {
+                    continue;
+                }
+            }
+            let match_kind = Some(self.solver.rules.match_kind(frame.target(), rule));
+            if rule.dependencies.is_empty() {
+                frame.solutions.push(Solution {
+                    rule: rule.clone(),
+                    args: SolutionArgsCollection::default(),
+                    match_kind,
+                    is_unresolved: false,
+                });
+            } else if self.beyond_preview_depth() {
+                let args = rule
+                    .dependencies
+                    .iter()
+                    .map(|dependency| SolutionArg {
+                        name: dependency.name.clone(),
+                        solution: Solution::unresolved(self.py, &dependency.typing),
+                    })
+                    .collect();
+                frame.solutions.push(Solution {
+                    rule: rule.clone(),
+                    args: SolutionArgsCollection::new(args),
+                    match_kind,
+                    is_unresolved: false,
+                });
+            } else {
+                if self.parallel && self.uses_memo() {
+                    self.prefetch(rule.dependencies.iter().map(|d| &d.typing).collect());
+                }
+                let mut dependencies = Vec::new();
+                for dependency in rule.dependencies.iter() {
+                    dependencies.push((self.estimate_candidates(&dependency.typing), dependency));
+                }
+                // Resolve the most constrained dependencies first so unsolvable edges fail fast.
+                dependencies.sort_by_key(|(estimate, _)| *estimate);
+                frame.rule = Some(RuleFrame {
+                    rule,
+                    match_kind,
+                    dependencies: dependencies
+                        .into_iter()
+                        .map(|(_, dependency)| dependency)
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                    pending: None,
+                    args: Vec::new(),
+                });
             }
         }
+    }
+
+    /// Apply the cardinality of a finished frame and memoize the result.
+    fn finish(&self, mut frame: TargetFrame<'a>) -> PyResult<Option<Vec<Solution>>> {
+        let mut solutions = dedupe_solutions(std::mem::take(&mut frame.solutions));
+        if let Some(Mock::Rule(_)) = self.mocks.get(&frame.target().type_hash) {
+            solutions.truncate(1);
+        }
+        let rejections = std::mem::take(&mut frame.rejections);
+        let target = frame.target();
+        let stack_dependent = self.stack_dependent.get();
+        self.stack_dependent
+            .set(frame.parent_stack_dependent || stack_dependent);
         if solutions.is_empty() {
-            self.push_error(SolvingErrorReason::NoSolution);
-            Ok(None)
-        } else {
-            let solutions = match target.solve_parameter.cardinality {
-                SolveCardinality::Exhaustive => solutions,
-                SolveCardinality::Single => match solutions.into_iter().next() {
-                    Some(r) => vec![r],
-                    None => Vec::new(),
-                },
-                SolveCardinality::Exclusive => {
-                    if solutions.len() > 1 {
-                        self.push_error(SolvingErrorReason::NotExclusive(solutions));
-                        return Ok(None);
-                    }
-                    solutions
+            if !stack_dependent {
+                self.failures.borrow_mut().insert(target.clone());
+            }
+            return Ok(self.no_solution(target, rejections));
+        }
+        let solutions = match target.solve_parameter.cardinality {
+            SolveCardinality::Exhaustive
+                if self.exceeds_max_solutions(target, solutions.len())
+                    && !self.solver.truncate_solutions =>
+            {
+                let max_solutions = self.solver.max_solutions.expect("limit was exceeded");
+                self.push_error(SolvingErrorReason::TooManySolutions(max_solutions));
+                return Ok(None);
+            }
+            SolveCardinality::Exhaustive => {
+                let mut solutions = solutions;
+                // Closest types still come first, stable so equal scores keep the rule order.
+                let breadth_first = self.solver.resolution_order == ResolutionOrder::BreadthFirst;
+                solutions.sort_by_cached_key(|solution| {
+                    (
+                        breadth_first.then(|| solution.depth()),
+                        self.solver.rules.specificity_distance(
+                            target.type_hash,
+                            solution.rule.output_type.type_hash,
+                        ),
+                        Reverse(solution.score()),
+                    )
+                });
+                let limit = match (target.solve_parameter.limit, self.solver.max_solutions) {
+                    (Some(limit), Some(max_solutions)) => Some(limit.min(max_solutions)),
+                    (limit, max_solutions) => limit.or(max_solutions),
+                };
+                match limit {
+                    Some(limit) => solutions.into_iter().take(limit).collect(),
+                    None => solutions,
                 }
-            };
-            self.solver.memo.save_memo(target, solutions.clone());
-            Ok(Some(solutions))
+            }
+            SolveCardinality::Single if target.solve_parameter.strict => {
+                // Preferred attributes and type distance may put a lower priority rule first.
+                let priority = solutions
+                    .iter()
+                    .map(|solution| solution.rule.priority)
+                    .max()
+                    .expect("solutions are not empty");
+                let tied: Vec<Solution> = solutions
+                    .into_iter()
+                    .filter(|solution| solution.rule.priority == priority)
+                    .collect();
+                if tied.len() > 1 {
+                    self.push_error(SolvingErrorReason::AmbiguousSingle(tied));
+                    return Ok(None);
+                }
+                tied
+            }
+            SolveCardinality::Single => match solutions.into_iter().next() {
+                Some(r) => vec![r],
+                None => Vec::new(),
+            },
+            SolveCardinality::Exclusive | SolveCardinality::AtMostOne => {
+                if solutions.len() > 1 {
+                    self.push_error(SolvingErrorReason::NotExclusive(solutions));
+                    return Ok(None);
+                }
+                solutions
+            }
+        };
+        if !stack_dependent && self.uses_memo() {
+            self.memo().save_memo(self.py, target, solutions.clone());
+        }
+        Ok(Some(solutions))
+    }
+
+    /// How many more argument combinations the cardinality of the target can use, given the
+    /// solutions found so far. None when every combination is needed, including to report
+    /// the conflicting solutions of a failure or to have some left after filtering.
+    fn permutations_needed(&self, target: &TypeInfo, found: usize) -> Option<usize> {
+        if self.filter.is_some() {
+            return None;
+        }
+        let parameter = &target.solve_parameter;
+        match parameter.cardinality {
+            // Only the first solution is kept, the rules are already in priority order.
+            SolveCardinality::Single if !parameter.strict => Some(1usize.saturating_sub(found)),
+            _ => None,
+        }
+    }
+
+    /// If an exhaustive target has more solutions than the solver allows.
+    fn exceeds_max_solutions(&self, target: &TypeInfo, count: usize) -> bool {
+        target.solve_parameter.cardinality == SolveCardinality::Exhaustive
+            && self
+                .solver
+                .max_solutions
+                .is_some_and(|max_solutions| count > max_solutions)
+    }
+
+    /// Targets allowing zero solutions resolve to an empty result.
+    fn empty_solutions(&self, target: &TypeInfo) -> Option<Vec<Solution>> {
+        if target.optional {
+            return Some(Vec::new());
         }
+        match target.solve_parameter.cardinality {
+            SolveCardinality::AtMostOne => Some(Vec::new()),
+            _ => None,
+        }
+    }
+
+    /// The rejections explain which rules the qualifiers of the target excluded.
+    fn no_solution(
+        &self,
+        target: &TypeInfo,
+        rejections: Vec<QualifierRejection>,
+    ) -> Option<Vec<Solution>> {
+        let solutions = self.empty_solutions(target);
+        if solutions.is_none() {
+            self.push_error(SolvingErrorReason::NoSolution(rejections));
+        }
+        solutions
+    }
+
+    /// The list type bound to a variadic dependency of the given element type.
+    fn variadic_list(&self, element: &TypeInfo) -> PyResult<TypeInfo> {
+        let mut list = TypeInfo::__new__(&self.py.get_type::<PyList>(), None)?;
+        list.element = Some(Box::new(element.clone()));
+        Ok(list)
+    }
+
+    /// Collect every solution of the element into a single collection solution.
+    fn collect_solution(
+        &self,
+        target: &TypeInfo,
+        element: &TypeInfo,
+        items: Vec<Solution>,
+    ) -> PyResult<Solution> {
+        let names: Vec<String> = (0..items.len())
+            .map(|index| format!("{COLLECT_ITEM_PREFIX}{index}"))
+            .collect();
+        let rule = Rule {
+            function: Arc::new(
+                Py::new(
+                    self.py,
+                    CollectValues {
+                        container: target.inner_type.clone_ref(self.py),
+                    },
+                )?
+                .into_any(),
+            ),
+            canonical_name: COLLECT_RULE_NAME.to_string(),
+            output_type: target.clone(),
+            dependencies: Dependencies {
+                dependencies: names
+                    .iter()
+                    .map(|name| Dependency {
+                        name: name.clone(),
+                        typing: element.clone(),
+                        variadic: false,
+                        optional: false,
+                    })
+                    .collect(),
+            },
+            priority: 0,
+            is_async: false,
+            guard: None,
+            tags: Default::default(),
+            enabled: None,
+            module: None,
+            qualname: None,
+            lineno: None,
+        };
+        let args = names
+            .into_iter()
+            .zip(items)
+            .map(|(name, solution)| SolutionArg { name, solution })
+            .collect();
+        Ok(Solution {
+            rule,
+            args: SolutionArgsCollection::new(args),
+            match_kind: Some(SolveSpecificity::Exact),
+            is_unresolved: false,
+        })
+    }
+}
+
+/// Context manager layering override rules in front of a solver's registry, see
+/// `Solver.with_overrides`.
+#[pyclass(module = "composify.core.solver")]
+pub struct SolverOverrides {
+    solver: Py<Solver>,
+    overlay: Arc<RuleRegistry>,
+    /// The registry of the solver while the overrides are entered.
+    previous: Option<Arc<RuleRegistry>>,
+}
+
+#[pymethods]
+impl SolverOverrides {
+    fn __enter__(&mut self, py: Python) -> PyResult<Py<Solver>> {
+        if self.previous.is_some() {
+            return Err(PyRuntimeError::new_err("Overrides are already entered."));
+        }
+        let mut solver = self.solver.try_borrow_mut(py)?;
+        self.previous = Some(std::mem::replace(&mut solver.rules, self.overlay.clone()));
+        // Overrides may provide types that were missing or failed, whose dependents are
+        // memoized without any trace of them.
+        solver.clear_cache(py);
+        Ok(self.solver.clone_ref(py))
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, py: Python, _args: &Bound<'_, PyTuple>) -> PyResult<bool> {
+        let mut solver = self.solver.try_borrow_mut(py)?;
+        if let Some(previous) = self.previous.take() {
+            solver.rules = previous;
+        }
+        solver.clear_cache(py);
+        Ok(false)
     }
 }
 
@@ -239,8 +1414,26 @@ impl<'a> _Solver<'a> {
 pub struct Solver {
     pub rules: Arc<RuleRegistry>,
     pub memo: SolutionsMemo,
+    pub async_memo: SolutionsMemo,
+    /// Maximum length of the execution stack, unbounded if None.
+    pub max_depth: Option<usize>,
+    /// Maximum number of argument permutations per rule, unbounded if None.
+    pub max_permutations: Option<usize>,
+    /// Called with the event, "push" or "pop", the name, and the target of every step.
+    pub trace_hook: Option<Arc<Py<PyAny>>>,
+    /// Resolve sibling dependencies concurrently, see `_Solver::prefetch`.
+    pub parallel: bool,
+    /// Maximum number of solutions of an exhaustive target, unbounded if None.
+    pub max_solutions: Option<usize>,
+    /// Keep the first `max_solutions` solutions instead of failing when there are more.
+    pub truncate_solutions: bool,
+    pub resolution_order: ResolutionOrder,
+    /// Solve parameters replacing the annotated ones of every target of a type, by type hash.
+    pub policies: HashMap<TypeHash, SolveParameter>,
 }
 
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
 fn make_trace_tuple<'a>(py: Python<'a>, stack: &ExecutionStack) -> PyResult<Bound<'a, PyTuple>> {
     let mut steps: Vec<Bound<PyTuple>> = Vec::new();
     for step in stack {
@@ -249,12 +1442,33 @@ fn make_trace_tuple<'a>(py: Python<'a>, stack: &ExecutionStack) -> PyResult<Boun
             [
                 step.name.into_pyobject(py)?.as_any(),
                 step.target.clone().into_pyobject(py)?.as_any(),
+                step.rule
+                    .map(|rule| rule.canonical_name.as_str())
+                    .into_pyobject(py)?
+                    .as_any(),
+                step.rule
+                    .and_then(Rule::location)
+                    .into_pyobject(py)?
+                    .as_any(),
             ],
         )?);
     }
     PyTuple::new(py, steps)
 }
 
+/// If the error is a solve failure caused only by types without solutions.
+fn only_missing_providers(py: Python, error: &PyErr) -> PyResult<bool> {
+    if !error.is_instance_of::<errors::SolveFailureError>(py) {
+        return Ok(false);
+    }
+    for error in error.value(py).getattr("errors")?.try_iter()? {
+        if !error?.is_instance_of::<errors::NoSolutionError>() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
 fn make_py_error(
     py: Python,
     stack: &ExecutionStack,
@@ -262,42 +1476,533 @@ fn make_py_error(
 ) -> PyResult<PyErr> {
     let traces = make_trace_tuple(py, stack)?;
     Ok(match reason {
-        SolvingErrorReason::NoSolution => {
-            errors::NoSolutionError::new_err(PyTuple::new(py, [traces])?.unbind())
+        SolvingErrorReason::NoSolution(rejections) => {
+            errors::NoSolutionError::new_err((traces, rejections).into_pyobject(py)?.unbind())
         }
-        SolvingErrorReason::CyclicDependency => {
-            errors::CyclicDependencyError::new_err(PyTuple::new(py, [traces])?.unbind())
+        SolvingErrorReason::CyclicDependency(cycle_start) => {
+            errors::CyclicDependencyError::new_err(
+                (traces, *cycle_start).into_pyobject(py)?.unbind(),
+            )
+        }
+        SolvingErrorReason::MaxDepthExceeded => {
+            errors::MaxDepthExceededError::new_err(PyTuple::new(py, [traces])?.unbind())
         }
         SolvingErrorReason::NotExclusive(solutions) => errors::NotExclusiveError::new_err(
             PyTuple::new(py, [PyTuple::new(py, solutions.clone())?, traces])?.unbind(),
         ),
+        SolvingErrorReason::AmbiguousSingle(solutions) => errors::AmbiguousSingleError::new_err(
+            PyTuple::new(py, [PyTuple::new(py, solutions.clone())?, traces])?.unbind(),
+        ),
+        SolvingErrorReason::TooManyPermutations(count) => {
+            errors::TooManyPermutationsError::new_err((*count, traces).into_pyobject(py)?.unbind())
+        }
+        SolvingErrorReason::TooManySolutions(max_solutions) => {
+            errors::TooManySolutionsError::new_err(
+                (*max_solutions, traces).into_pyobject(py)?.unbind(),
+            )
+        }
+        SolvingErrorReason::Timeout => {
+            errors::SolveTimeoutError::new_err(PyTuple::new(py, [traces])?.unbind())
+        }
     })
 }
 
 #[pymethods]
 impl Solver {
     #[new]
-    pub fn __new__(registry: &RuleRegistry) -> PyResult<Self> {
+    #[pyo3(signature = (
+        registry,
+        max_depth=Some(DEFAULT_MAX_DEPTH),
+        max_permutations=None,
+        memo_capacity=None,
+        trace_hook=None,
+        parallel=false,
+        max_solutions=None,
+        truncate_solutions=false,
+        resolution_order=ResolutionOrder::DepthFirst,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn __new__(
+        registry: &RuleRegistry,
+        max_depth: Option<usize>,
+        max_permutations: Option<usize>,
+        memo_capacity: Option<usize>,
+        trace_hook: Option<Py<PyAny>>,
+        parallel: bool,
+        max_solutions: Option<usize>,
+        truncate_solutions: bool,
+        resolution_order: ResolutionOrder,
+    ) -> PyResult<Self> {
         Ok(Self {
             rules: Arc::new(registry.clone()),
-            memo: SolutionsMemo::default(),
+            memo: SolutionsMemo::new(memo_capacity),
+            async_memo: SolutionsMemo::new(memo_capacity),
+            max_depth,
+            max_permutations,
+            trace_hook: trace_hook.map(Arc::new),
+            parallel,
+            max_solutions,
+            truncate_solutions,
+            resolution_order,
+            policies: HashMap::new(),
         })
     }
 
-    pub fn solve_for(&self, target: Bound<PyAny>) -> PyResult<Vec<Solution>> {
+    /// Register a rule, memoized solutions are kept until they are invalidated.
+    pub fn add_rule(&mut self, rule: &Bound<Rule>) -> PyResult<()> {
+        Arc::make_mut(&mut self.rules).add_rule(rule)
+    }
+
+    /// Evict the memoized solutions of the target and of every type depending on it.
+    /// The memo is keyed on the full TypeInfo, so attributes and qualifiers must match.
+    pub fn invalidate(&self, target: Bound<PyAny>) -> PyResult<()> {
         let py = target.py();
         let t = TypeInfo::parse(target)?;
-        let solver = _Solver::new(self, py);
+        self.memo.invalidate(py, &t, &self.rules);
+        self.async_memo.invalidate(py, &t, &self.rules);
+        Ok(())
+    }
+
+    /// Solve every target of the type with the parameter regardless of its annotation, or
+    /// with the annotated one again if None. The memoized solutions touching the type are
+    /// evicted.
+    #[pyo3(signature = (r#type, solve_parameter))]
+    pub fn set_policy(
+        &mut self,
+        r#type: Bound<PyAny>,
+        solve_parameter: Option<SolveParameter>,
+    ) -> PyResult<()> {
+        let py = r#type.py();
+        let t = TypeInfo::parse(r#type)?;
+        match solve_parameter {
+            Some(solve_parameter) => self.policies.insert(t.type_hash, solve_parameter),
+            None => self.policies.remove(&t.type_hash),
+        };
+        self.memo.invalidate(py, &t, &self.rules);
+        self.async_memo.invalidate(py, &t, &self.rules);
+        Ok(())
+    }
+
+    /// Evict every memoized solution.
+    pub fn clear_cache(&self, py: Python) {
+        self.memo.clear(py);
+        self.async_memo.clear(py);
+    }
+
+    /// Context manager replacing the registry with one holding the rules in front of it,
+    /// the registered rules of the same output types are shadowed until exit. The memo is
+    /// cleared on entry and exit. Rules added with `add_rule` inside the block go to the
+    /// override registry, so they are discarded on exit as well.
+    pub fn with_overrides(
+        slf: &Bound<'_, Self>,
+        rules: &Bound<'_, PyAny>,
+    ) -> PyResult<SolverOverrides> {
+        let mut overlay = RuleRegistry::overlay(slf.borrow().rules.clone());
+        overlay.add_rules(rules)?;
+        Ok(SolverOverrides {
+            solver: slf.clone().unbind(),
+            overlay: Arc::new(overlay),
+            previous: None,
+        })
+    }
+
+    /// Solve each target and return the registered rules that appear in none of the solutions.
+    pub fn unused_rules(&self, targets: Vec<Bound<PyAny>>) -> PyResult<Vec<Rule>> {
+        let mut used = HashSet::new();
+        for target in targets {
+            let (solutions, _) =
+                self.solve(target, ExecutionMode::Sync, SolveOptions::default())?;
+            for solution in solutions {
+                solution.any_node(|node| {
+                    used.insert(node.rule.clone());
+                    false
+                });
+            }
+        }
+        Ok(self
+            .rules
+            .all_rules()
+            .into_iter()
+            .filter(|rule| !used.contains(*rule))
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the memoized solution counts per type under "entries" and their sum under "total".
+    #[pyo3(signature = (is_async=false))]
+    pub fn memo_stats<'py>(&self, py: Python<'py>, is_async: bool) -> PyResult<Bound<'py, PyDict>> {
+        let memo = if is_async {
+            &self.async_memo
+        } else {
+            &self.memo
+        };
+        let entries = PyDict::new(py);
+        let mut total = 0;
+        for (t, count) in memo.solution_counts(py) {
+            entries.set_item(t, count)?;
+            total += count;
+        }
+        let stats = PyDict::new(py);
+        stats.set_item("entries", entries)?;
+        stats.set_item("total", total)?;
+        Ok(stats)
+    }
+
+    /// Solve preferring sync rules when a type has both sync and async rules.
+    #[pyo3(signature = (
+        target,
+        prefer_attributes=None,
+        max_preview_depth=None,
+        mocks=None,
+        filter=None,
+        timeout=None,
+        max_visits=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_for(
+        &self,
+        target: Bound<PyAny>,
+        prefer_attributes: Option<Vec<Bound<PyAny>>>,
+        max_preview_depth: Option<usize>,
+        mocks: Option<Bound<PyMapping>>,
+        filter: Option<Bound<PyAny>>,
+        timeout: Option<f64>,
+        max_visits: Option<usize>,
+    ) -> PyResult<Vec<Solution>> {
+        self.solve(
+            target,
+            ExecutionMode::Sync,
+            SolveOptions {
+                prefer_attributes,
+                max_preview_depth,
+                mocks,
+                filter,
+                timeout,
+                max_visits,
+            },
+        )
+        .map(|(solutions, _)| solutions)
+    }
+
+    /// Solve preferring async rules when a type has both sync and async rules.
+    #[pyo3(signature = (
+        target,
+        prefer_attributes=None,
+        max_preview_depth=None,
+        mocks=None,
+        filter=None,
+        timeout=None,
+        max_visits=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_for_async(
+        &self,
+        target: Bound<PyAny>,
+        prefer_attributes: Option<Vec<Bound<PyAny>>>,
+        max_preview_depth: Option<usize>,
+        mocks: Option<Bound<PyMapping>>,
+        filter: Option<Bound<PyAny>>,
+        timeout: Option<f64>,
+        max_visits: Option<usize>,
+    ) -> PyResult<Vec<Solution>> {
+        self.solve(
+            target,
+            ExecutionMode::Async,
+            SolveOptions {
+                prefer_attributes,
+                max_preview_depth,
+                mocks,
+                filter,
+                timeout,
+                max_visits,
+            },
+        )
+        .map(|(solutions, _)| solutions)
+    }
+
+    /// Solve each target in turn, sharing the work between them.
+    /// The errors of every failing target are combined into a single failure.
+    pub fn solve_many(&self, targets: Vec<Bound<PyAny>>) -> PyResult<Vec<Vec<Solution>>> {
+        let Some(py) = targets.first().map(|target| target.py()) else {
+            return Ok(Vec::new());
+        };
+        let targets = targets
+            .into_iter()
+            .map(TypeInfo::parse)
+            .collect::<PyResult<Vec<_>>>()?;
+        let solver = _Solver::new(self, py, ExecutionMode::Sync);
+        let mut results = Vec::with_capacity(targets.len());
+        let mut failed = false;
+        for target in &targets {
+            let errors = solver.errors.borrow().len();
+            solver.stack_dependent.set(false);
+            match solver.solve_for("__root__", target)? {
+                Some(solutions) => {
+                    // Errors of dead ends are only reported if the target fails.
+                    solver.errors.borrow_mut().truncate(errors);
+                    results.push(solutions);
+                }
+                None => failed = true,
+            }
+        }
+        if failed {
+            return Err(solver.failure()?);
+        }
+        Ok(results)
+    }
+
+    /// Describe the failures of solving the target as a tree following the execution stack,
+    /// None if the target is solvable.
+    pub fn explain(&self, target: Bound<PyAny>) -> PyResult<Option<Py<Explanation>>> {
+        let py = target.py();
+        let t = TypeInfo::parse(target)?;
+        let solver = _Solver::new(self, py, ExecutionMode::Sync);
+        if solver.solve_for("__root__", &t)?.is_some() {
+            return Ok(None);
+        }
+        let errors = solver.errors.borrow();
+        let Some((root, _)) = errors.iter().find(|(stack, _)| !stack.is_empty()) else {
+            return Ok(None);
+        };
+        let mut explanation = ExplanationNode::new(&root[0]);
+        for (stack, reason) in errors.iter() {
+            explanation.insert(stack, make_py_error(py, stack, reason)?);
+        }
+        explanation.build(py).map(Some)
+    }
+
+    /// Solve each target only to populate the memo, so later solves of the targets are memo
+    /// hits. Returns the targets paired with their `SolveFailureError`, None if solved.
+    pub fn preload(
+        &self,
+        py: Python,
+        targets: Vec<Bound<PyAny>>,
+    ) -> PyResult<Vec<(TypeInfo, Option<Py<PyBaseException>>)>> {
+        let mut report = Vec::new();
+        for target in targets {
+            let t = TypeInfo::parse(target.clone())?;
+            match self.solve(target, ExecutionMode::Sync, SolveOptions::default()) {
+                Ok(_) => report.push((t, None)),
+                Err(e) if e.is_instance_of::<errors::SolveFailureError>(py) => {
+                    report.push((t, Some(e.into_value(py))))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(report)
+    }
+
+    /// The highest priority solution of the target whatever its declared cardinality, None if
+    /// a type on the way has no provider. Other failures are raised as usual. A policy set for
+    /// the type still replaces the cardinality, so an exclusive policy may fail and any other
+    /// solves every candidate before the first is taken.
+    pub fn solve_first(&self, target: Bound<PyAny>) -> PyResult<Option<Solution>> {
+        let py = target.py();
+        let mut t = TypeInfo::parse(target)?;
+        t.solve_parameter.cardinality = SolveCardinality::Single;
+        t.solve_parameter.limit = None;
+        match self.solve_type(py, t, ExecutionMode::Sync, SolveOptions::default()) {
+            Ok((solutions, _)) => Ok(solutions.into_iter().next()),
+            Err(e) if only_missing_providers(py, &e)? => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Walk the rules reachable from the target without solving, collecting which types each
+    /// type depends on. Cycles are recorded instead of failing.
+    pub fn dependency_graph(&self, target: Bound<PyAny>) -> PyResult<DependencyGraph> {
+        let py = target.py();
+        let root = TypeInfo::parse(target)?;
+        let mut edges = HashMap::new();
+        let mut cycles = Vec::new();
+        let mut walking = HashSet::new();
+        let dependencies = self.structural_dependencies(py, &root)?;
+        edges.insert(root.clone(), dependencies.clone());
+        walking.insert(root.clone());
+        let mut stack = vec![(root, dependencies.into_iter())];
+        while let Some((node, pending)) = stack.last_mut() {
+            let Some(dependency) = pending.next() else {
+                let (node, _) = stack.pop().expect("stack is not empty");
+                walking.remove(&node);
+                continue;
+            };
+            if walking.contains(&dependency) {
+                cycles.push((node.clone(), dependency));
+            } else if !edges.contains_key(&dependency) {
+                let dependencies = self.structural_dependencies(py, &dependency)?;
+                edges.insert(dependency.clone(), dependencies.clone());
+                walking.insert(dependency.clone());
+                stack.push((dependency, dependencies.into_iter()));
+            }
+        }
+        Ok(DependencyGraph { edges, cycles })
+    }
+
+    /// Solve like solve_for, also returning counters collected while solving.
+    pub fn solve_for_with_stats(&self, target: Bound<PyAny>) -> PyResult<(Vec<Solution>, Stats)> {
+        self.solve(target, ExecutionMode::Sync, SolveOptions::default())
+    }
+
+    /// Solve the target and build it, awaiting async rules along the way. Async qualifiers
+    /// met while solving are awaited before solving again, see `AsyncResolution`.
+    #[pyo3(signature = (target, prefer_attributes=None, mocks=None))]
+    pub fn resolve_async<'py>(
+        slf: &Bound<'py, Self>,
+        target: Bound<'py, PyAny>,
+        prefer_attributes: Option<Vec<Bound<'py, PyAny>>>,
+        mocks: Option<Bound<'py, PyMapping>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let py = slf.py();
+        let mut resolution = AsyncResolution {
+            solver: slf.clone().unbind(),
+            target: target.unbind(),
+            prefer_attributes: prefer_attributes
+                .map(|attributes| attributes.into_iter().map(Bound::unbind).collect()),
+            mocks: mocks.map(Bound::unbind),
+            awaited: AwaitedQualifiers::default(),
+            solution: None,
+        };
+        match resolution.attempt(py)? {
+            None => resolution.build(py),
+            Some(pending) => py
+                .import("composify.builder")?
+                .getattr("resolve_awaiting_qualifiers")?
+                .call1((resolution, pending)),
+        }
+    }
+}
+
+/// A `Solver.resolve_async` stopped at an async qualifier. Solving is synchronous, so the
+/// qualifier's awaitable is awaited outside of it and the solve runs again knowing its
+/// result, until no qualifier is left to await.
+#[pyclass(module = "composify.core.solver")]
+pub struct AsyncResolution {
+    solver: Py<Solver>,
+    target: Py<PyAny>,
+    prefer_attributes: Option<Vec<Py<PyAny>>>,
+    mocks: Option<Py<PyMapping>>,
+    awaited: AwaitedQualifiers,
+    solution: Option<Solution>,
+}
+
+impl AsyncResolution {
+    /// Solve with the qualifiers awaited so far, returns the next awaitable to await or None
+    /// once solved.
+    fn attempt(&mut self, py: Python) -> PyResult<Option<Py<PyAny>>> {
+        let solver = self.solver.borrow(py);
+        let target = self.target.bind(py);
+        let options = SolveOptions {
+            prefer_attributes: self
+                .prefer_attributes
+                .as_ref()
+                .map(|attributes| attributes.iter().map(|a| a.bind(py).clone()).collect()),
+            mocks: self.mocks.as_ref().map(|mocks| mocks.bind(py).clone()),
+            ..Default::default()
+        };
+        let solved = self
+            .awaited
+            .evaluate(|| solver.solve(target.clone(), ExecutionMode::Async, options));
+        if let Some(pending) = self.awaited.pending(py) {
+            return Ok(Some(pending));
+        }
+        let (solutions, _) = solved?;
+        let Some(solution) = solutions.into_iter().next() else {
+            let t = TypeInfo::parse(target.clone())?;
+            let traces = PyTuple::new(py, [("__root__", t, py.None(), py.None())])?;
+            let error = errors::NoSolutionError::new_err(PyTuple::new(py, [traces])?.unbind());
+            return Err(errors::SolveFailureError::new_err(vec![error]));
+        };
+        self.solution = Some(solution);
+        Ok(None)
+    }
+}
+
+#[pymethods]
+impl AsyncResolution {
+    /// Solve again with the result of the awaited qualifier, returns the next awaitable to
+    /// await or None once solved.
+    pub fn resume(&mut self, qualified: Bound<PyAny>) -> PyResult<Option<Py<PyAny>>> {
+        self.awaited.resolve(qualified.is_truthy()?);
+        self.attempt(qualified.py())
+    }
+
+    /// Build the solution, awaiting async rules along the way.
+    pub fn build<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let Some(solution) = &self.solution else {
+            return Err(PyRuntimeError::new_err("The target is not solved yet."));
+        };
+        let builder = py
+            .import("composify.builder")?
+            .getattr("AsyncBuilder")?
+            .call0()?;
+        builder.call_method1("from_solution", (solution.clone(),))
+    }
+}
+
+/// Options applying to a single solve.
+#[derive(Default)]
+struct SolveOptions<'py> {
+    prefer_attributes: Option<Vec<Bound<'py, PyAny>>>,
+    max_preview_depth: Option<usize>,
+    mocks: Option<Bound<'py, PyMapping>>,
+    filter: Option<Bound<'py, PyAny>>,
+    /// Seconds before solving aborts.
+    timeout: Option<f64>,
+    max_visits: Option<usize>,
+}
+
+impl Solver {
+    /// The distinct dependency types of the rules providing the type, in rule order.
+    fn structural_dependencies(&self, py: Python, typing: &TypeInfo) -> PyResult<Vec<TypeInfo>> {
+        let rules = self.rules.get(py, typing)?.unwrap_or_default();
+        if rules.is_empty() {
+            return Ok(typing.element.as_deref().cloned().into_iter().collect());
+        }
+        let mut dependencies: Vec<TypeInfo> = Vec::new();
+        for rule in rules {
+            for dependency in rule.dependencies.iter() {
+                if !dependencies.contains(&dependency.typing) {
+                    dependencies.push(dependency.typing.clone());
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
+    fn solve(
+        &self,
+        target: Bound<PyAny>,
+        mode: ExecutionMode,
+        options: SolveOptions,
+    ) -> PyResult<(Vec<Solution>, Stats)> {
+        self.solve_type(target.py(), TypeInfo::parse(target)?, mode, options)
+    }
+
+    fn solve_type(
+        &self,
+        py: Python,
+        t: TypeInfo,
+        mode: ExecutionMode,
+        options: SolveOptions,
+    ) -> PyResult<(Vec<Solution>, Stats)> {
+        let mut solver = _Solver::new(self, py, mode);
+        solver.prefer_attributes = MetadataSet::new(options.prefer_attributes.unwrap_or_default())?;
+        solver.max_preview_depth = options.max_preview_depth;
+        if let Some(mocks) = options.mocks {
+            solver.mocks = parse_mocks(&mocks)?;
+        }
+        solver.filter = options.filter.map(Bound::unbind);
+        if let Some(timeout) = options.timeout {
+            let timeout = Duration::try_from_secs_f64(timeout)
+                .map_err(|e| PyValueError::new_err(format!("Invalid timeout {timeout}: {e}")))?;
+            // A timeout too far in the future to represent never expires.
+            solver.deadline = Instant::now().checked_add(timeout);
+        }
+        solver.max_visits = options.max_visits;
         if let Some(solutions) = solver.solve_for("__root__", &t)? {
-            Ok(solutions)
+            Ok((solutions, solver.stats.take()))
         } else {
-            let errors: PyResult<Vec<PyErr>> = solver
-                .errors
-                .borrow()
-                .iter()
-                .map(|(s, r)| make_py_error(py, s, r))
-                .collect();
-            Err(errors::SolveFailureError::new_err(errors?))
+            Err(solver.failure()?)
         }
     }
 }
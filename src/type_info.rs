@@ -1,36 +1,106 @@
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
-use pyo3::types::PyType;
+use pyo3::types::{PyFrozenSet, PyList, PySet, PyTuple, PyType};
 use pyo3::{intern, types::PySequence};
 use std::fmt::Display;
 use std::hash::Hash;
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
 
-use crate::metadata::{MetadataSet, Qualifiers, QUALIFY_METHOD_NAME};
-use crate::solve_parameters::{SolveCardinality, SolveParameter, SolveSpecificity};
+use crate::metadata::{MetadataSet, Qualifier, QualifierMode, Qualifiers};
+use crate::solve_parameters::{
+    DependencyMarker, First, SolveCardinality, SolveParameter, SolveSpecificity, Strict,
+};
+
+/// Built-in schemes for rendering the canonical name of a type.
+#[pyclass(hash, eq, eq_int, frozen, module = "composify.core")]
+#[derive(PartialEq, Clone, Debug, Hash, Default)]
+pub enum NamingScheme {
+    /// `module.Name`, omitting the module for builtins.
+    #[default]
+    Short,
+    /// Always `module.Name`.
+    FullyQualified,
+}
+
+const BUILTINS_MODULE: &str = "builtins";
+
+/// The `__module__` of the type. Extension types without a string module are treated as
+/// builtins, types of scripts keep `__main__`.
+fn type_module(typing: &Bound<'_, PyType>) -> String {
+    typing
+        .module()
+        .map(|module| module.to_string())
+        .unwrap_or_else(|_| BUILTINS_MODULE.to_string())
+}
+
+#[derive(Clone)]
+enum CanonicalNamer {
+    Scheme(NamingScheme),
+    Custom(Arc<Py<PyAny>>),
+}
+
+static CANONICAL_NAMER: RwLock<CanonicalNamer> =
+    RwLock::new(CanonicalNamer::Scheme(NamingScheme::Short));
+
+/// Set how canonical names are rendered, either a NamingScheme or a callable
+/// receiving the type and returning its name. None restores the default.
+#[pyfunction]
+#[pyo3(signature = (strategy=None))]
+pub fn set_canonical_name_strategy(strategy: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+    let namer = match strategy {
+        None => CanonicalNamer::Scheme(NamingScheme::default()),
+        Some(strategy) => {
+            if let Ok(scheme) = strategy.downcast::<NamingScheme>() {
+                CanonicalNamer::Scheme(scheme.get().clone())
+            } else if strategy.is_callable() {
+                CanonicalNamer::Custom(Arc::new(strategy.unbind()))
+            } else {
+                return Err(PyTypeError::new_err(format!(
+                    "Expected NamingScheme or callable, got {strategy}"
+                )));
+            }
+        }
+    };
+    *CANONICAL_NAMER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = namer;
+    Ok(())
+}
 
 fn parse_metadata(
     metadata: &Bound<'_, PySequence>,
 ) -> PyResult<(MetadataSet, Qualifiers, SolveParameter)> {
-    let py = metadata.py();
     let mut attributes = Vec::new();
     let mut qualifiers = Vec::new();
     let mut solve_parameter = SolveParameter::default();
-    for py_element in metadata.try_iter()?.flatten() {
-        if py_element.hasattr(intern!(py, QUALIFY_METHOD_NAME))? {
-            qualifiers.push(py_element);
+    for (index, py_element) in metadata.try_iter()?.flatten().enumerate() {
+        if Qualifier::is_qualifier(&py_element)? {
+            qualifiers.push((index, py_element));
         } else if let Ok(c) = py_element.downcast::<SolveCardinality>() {
             let c = c.get();
             solve_parameter.cardinality = c.clone();
+            solve_parameter.limit = None;
+            solve_parameter.strict = false;
+        } else if let Ok(first) = py_element.downcast::<First>() {
+            solve_parameter.cardinality = SolveCardinality::Exhaustive;
+            solve_parameter.limit = Some(first.get().n);
+            solve_parameter.strict = false;
+        } else if py_element.is_instance_of::<Strict>() {
+            solve_parameter.cardinality = SolveCardinality::Single;
+            solve_parameter.limit = None;
+            solve_parameter.strict = true;
         } else if let Ok(s) = py_element.downcast::<SolveSpecificity>() {
             let s = s.get();
             solve_parameter.specificity = s.clone();
+        } else if py_element.is_instance_of::<DependencyMarker>() {
+            // Read by the dependency itself.
         } else {
-            attributes.push(py_element);
+            attributes.push((index, py_element));
         }
     }
     Ok((
-        MetadataSet::new(attributes)?,
-        Qualifiers::__new__(qualifiers)?,
+        MetadataSet::from_indexed(attributes)?,
+        Qualifiers::from_indexed(qualifiers, QualifierMode::All)?,
         solve_parameter,
     ))
 }
@@ -52,6 +122,140 @@ pub struct TypeInfo {
     pub solve_parameter: SolveParameter,
 
     pub inner_type: Arc<Py<PyType>>,
+    /// Element type of `list[X]`, `set[X]`, `frozenset[X]` and `tuple[X, ...]`.
+    pub element: Option<Box<TypeInfo>>,
+    /// Parsed from `Optional[X]`, an unsatisfiable optional type resolves to no solutions.
+    #[pyo3(get)]
+    pub optional: bool,
+    /// Arguments of a parametrized generic such as `dict[str, int]`.
+    pub type_args: Option<TypeArgs>,
+}
+
+/// Arguments of a parametrized generic, compared by value once their Python hashes match.
+#[derive(Debug, Clone)]
+pub struct TypeArgs {
+    pub args: Arc<Py<PyTuple>>,
+    hash: isize,
+}
+
+impl TypeArgs {
+    pub fn new(args: Bound<'_, PyTuple>) -> PyResult<Option<Self>> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            hash: args.hash()?,
+            args: Arc::new(args.unbind()),
+        }))
+    }
+
+    fn to_type_string(&self) -> String {
+        Python::with_gil(|py| {
+            self.args
+                .bind(py)
+                .iter()
+                .map(|arg| {
+                    if arg.is(py.Ellipsis()) {
+                        "...".to_string()
+                    } else if let Ok(type_info) = TypeInfo::parse(arg.clone()) {
+                        type_info.canonical_name()
+                    } else {
+                        arg.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    }
+}
+
+impl Hash for TypeArgs {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+/// Arguments whose comparison raises are only equal to themselves.
+impl PartialEq for TypeArgs {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.args, &other.args)
+            || (self.hash == other.hash
+                && Python::with_gil(|py| {
+                    let args = self.args.bind(py);
+                    let others = other.args.bind(py);
+                    args.is(others) || args.eq(others).unwrap_or(false)
+                }))
+    }
+}
+
+impl Eq for TypeArgs {}
+
+/// Parse the inner type of `Optional[X]` and `X | None`.
+fn parse_optional(annotation: &Bound<'_, PyAny>) -> PyResult<Option<TypeInfo>> {
+    let py = annotation.py();
+    let typing = py.import(intern!(py, "typing"))?;
+    let origin = typing.call_method1(intern!(py, "get_origin"), (annotation,))?;
+    if !(origin.is(typing.getattr(intern!(py, "Union"))?)
+        || origin.is(py
+            .import(intern!(py, "types"))?
+            .getattr(intern!(py, "UnionType"))?))
+    {
+        return Ok(None);
+    }
+    let args = typing
+        .call_method1(intern!(py, "get_args"), (annotation,))?
+        .downcast_into::<PyTuple>()?;
+    let none_type = py.None().into_bound(py).get_type();
+    let mut inner = None;
+    for arg in args.iter() {
+        if arg.is(&none_type) {
+            continue;
+        }
+        if inner.is_some() {
+            return Ok(None);
+        }
+        inner = Some(arg);
+    }
+    match inner {
+        Some(inner) if args.len() == 2 => {
+            let mut type_info = TypeInfo::parse(inner)?;
+            type_info.optional = true;
+            Ok(Some(type_info))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parse the element type if the alias is a homogeneous builtin collection.
+fn parse_element(
+    origin: &Bound<'_, PyType>,
+    alias: &Bound<'_, PyAny>,
+) -> PyResult<Option<Box<TypeInfo>>> {
+    let py = origin.py();
+    let args = py
+        .import(intern!(py, "typing"))?
+        .call_method1(intern!(py, "get_args"), (alias,))?
+        .downcast_into::<PyTuple>()?;
+    let element = if origin.is(py.get_type::<PyList>())
+        || origin.is(py.get_type::<PySet>())
+        || origin.is(py.get_type::<PyFrozenSet>())
+    {
+        match args.len() {
+            1 => args.get_item(0)?,
+            _ => return Ok(None),
+        }
+    } else if origin.is(py.get_type::<PyTuple>()) {
+        match args.len() {
+            2 if args.get_item(1)?.is(py.Ellipsis()) => args.get_item(0)?,
+            _ => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+    let mut element = TypeInfo::parse(element)?;
+    // A collection holds every solution of its element.
+    element.solve_parameter.cardinality = SolveCardinality::Exhaustive;
+    Ok(Some(Box::new(element)))
 }
 
 #[pymethods]
@@ -72,40 +276,73 @@ impl TypeInfo {
         };
         Ok(TypeInfo {
             type_name: type_annotation.name()?.to_string(),
-            type_module: type_annotation.module()?.to_string(),
+            type_module: type_module(type_annotation),
             type_hash: type_annotation.hash()?,
             inner_type: Arc::new(type_annotation.clone().unbind()),
             attributes,
             qualifiers,
             solve_parameter,
+            element: None,
+            optional: false,
+            type_args: None,
         })
     }
 
+    /// A copy of this type with the metadata replaced, parsed like the metadata of
+    /// `Annotated`. The element, optional marker and generic arguments are kept.
+    pub fn with_metadata(&self, items: Bound<'_, PySequence>) -> PyResult<TypeInfo> {
+        let (attributes, qualifiers, solve_parameter) = parse_metadata(&items)?;
+        Ok(TypeInfo {
+            attributes,
+            qualifiers,
+            solve_parameter,
+            ..self.clone()
+        })
+    }
+
+    /// A copy of this type solved with the given parameter.
+    pub fn with_solve_parameter(&self, solve_parameter: SolveParameter) -> TypeInfo {
+        TypeInfo {
+            solve_parameter,
+            ..self.clone()
+        }
+    }
+
     #[staticmethod]
     pub fn parse(type_annotation: Bound<'_, PyAny>) -> PyResult<TypeInfo> {
         let py = type_annotation.py();
-        let t = match type_annotation.downcast::<PyType>() {
-            Ok(t) => t,
-            Err(..) => {
-                if type_annotation.hasattr(intern!(py, "__origin__"))? {
-                    let origin = type_annotation.getattr(intern!(py, "__origin__"))?;
-                    &origin.downcast_into::<PyType>()?
-                } else {
-                    let a = type_annotation.downcast_into::<TypeInfo>()?;
-                    return Ok(a.get().clone());
-                }
-            }
-        };
-        let metadata = if type_annotation.hasattr(intern!(py, "__metadata__"))? {
-            Some(
-                type_annotation
-                    .getattr(intern!(py, "__metadata__"))?
-                    .downcast_into::<PySequence>()?,
-            )
-        } else {
-            None
-        };
-        TypeInfo::__new__(t, metadata)
+        if let Ok(t) = type_annotation.downcast::<PyType>() {
+            return TypeInfo::__new__(t, None);
+        }
+        if type_annotation.hasattr(intern!(py, "__metadata__"))? {
+            let base = TypeInfo::parse(type_annotation.getattr(intern!(py, "__origin__"))?)?;
+            let metadata = type_annotation
+                .getattr(intern!(py, "__metadata__"))?
+                .downcast_into::<PySequence>()?;
+            let mut type_info = TypeInfo::__new__(base.inner_type.bind(py), Some(metadata))?;
+            type_info.element = base.element;
+            type_info.optional = base.optional;
+            type_info.type_args = base.type_args;
+            return Ok(type_info);
+        }
+        if let Some(type_info) = parse_optional(&type_annotation)? {
+            return Ok(type_info);
+        }
+        if type_annotation.hasattr(intern!(py, "__origin__"))? {
+            let origin = type_annotation
+                .getattr(intern!(py, "__origin__"))?
+                .downcast_into::<PyType>()?;
+            let mut type_info = TypeInfo::__new__(&origin, None)?;
+            type_info.element = parse_element(&origin, &type_annotation)?;
+            type_info.type_args = TypeArgs::new(
+                py.import(intern!(py, "typing"))?
+                    .call_method1(intern!(py, "get_args"), (&type_annotation,))?
+                    .downcast_into::<PyTuple>()?,
+            )?;
+            return Ok(type_info);
+        }
+        let a = type_annotation.downcast_into::<TypeInfo>()?;
+        Ok(a.get().clone())
     }
 
     pub fn __repr__(&self) -> PyResult<String> {
@@ -120,43 +357,94 @@ impl TypeInfo {
     pub fn get_inner_type(&self, py: Python) -> Py<PyType> {
         self.inner_type.clone_ref(py)
     }
+
+    /// The name of the type under the current canonical name strategy.
+    #[getter(canonical_name)]
+    pub fn get_canonical_name(&self) -> String {
+        self.canonical_name()
+    }
+
+    /// If the type is defined in the `builtins` module.
+    pub fn is_builtin(&self) -> bool {
+        self.type_module == BUILTINS_MODULE
+    }
+
+    #[getter(element)]
+    pub fn get_element(&self) -> Option<TypeInfo> {
+        self.element.as_deref().cloned()
+    }
+
+    #[getter(type_args)]
+    pub fn get_type_args<'py>(&self, py: Python<'py>) -> Bound<'py, PyTuple> {
+        match &self.type_args {
+            Some(type_args) => type_args.args.bind(py).clone(),
+            None => PyTuple::empty(py),
+        }
+    }
 }
 
 impl TypeInfo {
-    #[inline(always)]
     pub fn canonical_name(&self) -> String {
-        if self.type_module == "builtins" {
-            self.type_name.clone()
-        } else {
-            format!("{}.{}", self.type_module, self.type_name)
+        // Cloned out so the lock is not held while calling into Python.
+        let namer = CANONICAL_NAMER
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let scheme = match namer {
+            CanonicalNamer::Scheme(scheme) => scheme,
+            CanonicalNamer::Custom(namer) => {
+                // Fall back to the default scheme if the namer misbehaves.
+                let name = Python::with_gil(|py| {
+                    namer
+                        .call1(py, (self.inner_type.clone_ref(py),))?
+                        .extract::<String>(py)
+                });
+                match name {
+                    Ok(name) => return name,
+                    Err(_) => NamingScheme::default(),
+                }
+            }
+        };
+        match scheme {
+            NamingScheme::Short if self.is_builtin() => self.type_name.clone(),
+            _ => format!("{}.{}", self.type_module, self.type_name),
         }
     }
 
     pub fn to_type_string(&self) -> String {
-        let mut annotations: Vec<String> = Vec::new();
-        if !self.attributes.is_empty() {
-            for attr in self.attributes.iter() {
-                annotations.push(attr.to_string());
+        // Each group is sorted so the rendering does not depend on the storage order.
+        let mut annotations: Vec<String> = self.attributes.iter().map(|a| a.to_string()).collect();
+        annotations.sort_unstable();
+        let mut qualifiers: Vec<String> = self.qualifiers.iter().map(|q| q.to_string()).collect();
+        qualifiers.sort_unstable();
+        annotations.extend(qualifiers);
+        let name = match (&self.element, &self.type_args) {
+            (Some(element), _) => {
+                format!("{}[{}]", self.canonical_name(), element.to_type_string())
             }
-        }
-        if !self.qualifiers.is_empty() {
-            for qualifier in self.qualifiers.iter() {
-                annotations.push(qualifier.to_string());
+            (None, Some(type_args)) => {
+                format!("{}[{}]", self.canonical_name(), type_args.to_type_string())
             }
-        }
+            (None, None) => self.canonical_name(),
+        };
+        let name = if self.optional {
+            format!("{}?", name)
+        } else {
+            name
+        };
         if annotations.is_empty() {
             format!(
                 "{}({}{})",
-                self.canonical_name(),
+                name,
                 self.solve_parameter.specificity.symbol(),
-                self.solve_parameter.cardinality.symbol()
+                self.solve_parameter.cardinality_symbol()
             )
         } else {
             format!(
                 "{}({}{}, {})",
-                self.canonical_name(),
+                name,
                 self.solve_parameter.specificity.symbol(),
-                self.solve_parameter.cardinality.symbol(),
+                self.solve_parameter.cardinality_symbol(),
                 annotations.join(", ")
             )
         }
@@ -165,10 +453,20 @@ impl TypeInfo {
 
 impl Display for TypeInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypeInfo({}", self.inner_type)?;
+        if let Some(element) = &self.element {
+            write!(f, ", element={}", element)?;
+        }
+        if let Some(type_args) = &self.type_args {
+            write!(f, ", args={}", type_args.args)?;
+        }
+        if self.optional {
+            write!(f, ", optional")?;
+        }
         write!(
             f,
-            "TypeInfo({}, attrs={}, qualifiers={}, solve={})",
-            self.inner_type, self.attributes, self.qualifiers, self.solve_parameter,
+            ", attrs={}, qualifiers={}, solve={})",
+            self.attributes, self.qualifiers, self.solve_parameter,
         )
     }
 }
@@ -181,6 +479,10 @@ impl Hash for TypeInfo {
         self.type_hash.hash(state);
         self.attributes.hash(state);
         self.qualifiers.hash(state);
+        self.solve_parameter.hash(state);
+        self.element.hash(state);
+        self.optional.hash(state);
+        self.type_args.hash(state);
     }
 }
 
@@ -189,6 +491,10 @@ impl PartialEq for TypeInfo {
         self.type_hash == other.type_hash
             && self.attributes == other.attributes
             && self.qualifiers == other.qualifiers
+            && self.solve_parameter == other.solve_parameter
+            && self.element == other.element
+            && self.optional == other.optional
+            && self.type_args == other.type_args
     }
 }
 
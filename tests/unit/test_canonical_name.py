@@ -0,0 +1,50 @@
+from collections import OrderedDict
+from dataclasses import dataclass
+from decimal import Decimal
+
+import pytest
+
+from composify.core import NamingScheme, TypeInfo, set_canonical_name_strategy
+
+
+@dataclass(frozen=True)
+class Named:
+    value: int
+
+
+def test_default_canonical_name():
+    assert str(TypeInfo(int)) == "int(+x)"
+    assert str(TypeInfo(Named)) == f"{__name__}.Named(+x)"
+
+
+def test_canonical_name_property():
+    assert TypeInfo(int).canonical_name == "int"
+    assert TypeInfo(int).is_builtin()
+    assert TypeInfo(OrderedDict).canonical_name == "collections.OrderedDict"
+    assert TypeInfo(Decimal).canonical_name == "decimal.Decimal"
+    assert not TypeInfo(Decimal).is_builtin()
+    assert TypeInfo(Named).canonical_name == f"{__name__}.Named"
+    assert not TypeInfo(Named).is_builtin()
+
+
+def test_fully_qualified_canonical_name():
+    set_canonical_name_strategy(NamingScheme.FullyQualified)
+    try:
+        assert str(TypeInfo(int)) == "builtins.int(+x)"
+    finally:
+        set_canonical_name_strategy()
+
+
+def test_custom_canonical_name():
+    set_canonical_name_strategy(lambda t: f"custom:{t.__qualname__}")
+    try:
+        assert str(TypeInfo(Named)) == "custom:Named(+x)"
+    finally:
+        set_canonical_name_strategy()
+
+    assert str(TypeInfo(Named)) == f"{__name__}.Named(+x)"
+
+
+def test_invalid_canonical_name_strategy():
+    with pytest.raises(TypeError):
+        set_canonical_name_strategy("not a strategy")
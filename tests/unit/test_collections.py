@@ -0,0 +1,146 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.builder import Builder
+from composify.core import (
+    DependencyMarker,
+    MetadataSet,
+    SolveCardinality,
+    TypeInfo,
+)
+from composify.errors import SolveFailureError
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Handler:
+    name: str
+
+
+@dataclass(frozen=True)
+class Registry:
+    handlers: tuple[Handler, ...]
+
+
+@rule(priority=3)
+def first_handler() -> Handler:
+    return Handler("first")
+
+
+@rule(priority=2)
+def second_handler() -> Handler:
+    return Handler("second")
+
+
+@rule(priority=1)
+def third_handler() -> Handler:
+    return Handler("third")
+
+
+@rule
+def create_registry(handlers: tuple[Handler, ...]) -> Registry:
+    return Registry(handlers)
+
+
+handler_rules = (first_handler, second_handler, third_handler)
+
+
+def test_collection_type_info():
+    type_info = TypeInfo.parse(list[Handler])
+
+    assert type_info.inner_type is list
+    assert type_info.element == TypeInfo(
+        Handler, [SolveCardinality.Exhaustive]
+    )
+    assert (
+        type_info.element.solve_parameter.cardinality
+        == SolveCardinality.Exhaustive
+    )
+    assert type_info != TypeInfo.parse(list[Registry])
+    assert TypeInfo.parse(tuple[Handler, int]).element is None
+
+
+def test_solve_list():
+    solver = create_rule_solver(*handler_rules)
+
+    [solution] = solver.solve_for(list[Handler])
+    assert len(list(solution.args)) == 3
+
+    handlers = Builder().from_solution(solution)
+    assert handlers == [
+        Handler("first"),
+        Handler("second"),
+        Handler("third"),
+    ]
+
+
+def test_solve_set():
+    solver = create_rule_solver(*handler_rules)
+
+    [solution] = solver.solve_for(Annotated[set[Handler], "handlers"])
+
+    assert Builder().from_solution(solution) == {
+        Handler("first"),
+        Handler("second"),
+        Handler("third"),
+    }
+
+
+def test_solve_collection_dependency():
+    solver = create_rule_solver(*handler_rules, create_registry)
+
+    [solution] = solver.solve_for(Registry)
+
+    assert Builder().from_solution(solution) == Registry(
+        (Handler("first"), Handler("second"), Handler("third"))
+    )
+
+
+def test_solve_empty_collection():
+    solver = create_rule_solver(create_registry)
+
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(list[Handler])
+
+
+@dataclass(frozen=True)
+class Dispatcher:
+    handlers: list[Handler]
+
+
+@rule
+def create_dispatcher(
+    handlers: Annotated[Handler, DependencyMarker.Variadic],
+) -> Dispatcher:
+    return Dispatcher(handlers)
+
+
+def test_solve_variadic_dependency():
+    solver = create_rule_solver(*handler_rules, create_dispatcher)
+
+    [solution] = solver.solve_for(Dispatcher)
+
+    assert Builder().from_solution(solution) == Dispatcher(
+        [Handler("first"), Handler("second"), Handler("third")]
+    )
+
+
+def test_variadic_dependency_type_info():
+    [dependency] = as_rule(create_dispatcher).dependencies
+    assert dependency.variadic
+    assert dependency.typing.inner_type is Handler
+    assert dependency.typing.attributes == MetadataSet([])
+    assert (
+        dependency.typing.solve_parameter.cardinality
+        == SolveCardinality.Exhaustive
+    )
+
+
+def test_solve_empty_variadic_dependency():
+    solver = create_rule_solver(create_dispatcher)
+
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(Dispatcher)
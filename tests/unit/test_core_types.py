@@ -1,11 +1,25 @@
 from dataclasses import dataclass
-from typing import Annotated
+from typing import (
+    Annotated,
+    Dict,
+    Generic,
+    List,
+    Literal,
+    Optional,
+    TypeVar,
+)
+
+import pytest
 
 from composify.core import (
+    Dependency,
     MetadataSet,
     RuleRegistry,
+    Solution,
     SolveCardinality,
+    SolveParameter,
     SolveSpecificity,
+    Solver,
     TypeInfo,
 )
 from composify.rules import Rule
@@ -63,6 +77,28 @@ def test_solve_parameter():
     )
 
 
+def test_type_info_with_metadata():
+    exhaustive = TypeInfo.parse(
+        Annotated[str, NameAttr("a"), SolveCardinality.Exhaustive]
+    )
+    exclusive = exhaustive.with_solve_parameter(
+        SolveParameter(
+            SolveSpecificity.AllowSubclass, SolveCardinality.Exclusive
+        )
+    )
+
+    assert exclusive.inner_type is exhaustive.inner_type
+    assert exclusive.attributes == exhaustive.attributes
+    assert exclusive.solve_parameter.cardinality == SolveCardinality.Exclusive
+    assert exclusive != exhaustive
+    assert hash(exclusive) != hash(exhaustive)
+    assert exclusive == TypeInfo.parse(Annotated[str, NameAttr("a")])
+
+    renamed = exhaustive.with_metadata([NameAttr("b")])
+    assert renamed == TypeInfo.parse(Annotated[str, NameAttr("b")])
+    assert exhaustive.with_metadata([]) == TypeInfo.parse(str)
+
+
 def test_registry_qualifier():
     reg = RuleRegistry()
     r1 = Rule(example_fn, "test", str, {"in1": str, "in2": str}, 3, False)
@@ -80,3 +116,153 @@ def test_registry_qualifier():
     assert hash((r2,)) == hash(
         reg.get_rules(Annotated[str, NameQualifier("test2")])
     )
+
+
+def test_solution_equals_ignoring_args():
+    r = Rule(example_fn, "test", str, {"in1": str}, 3, False)
+    a = Rule(example_fn, "a", Annotated[str, NameAttr("a")], {}, 0, False)
+    b = Rule(example_fn, "b", Annotated[str, NameAttr("b")], {}, 0, False)
+    s1 = Solution(r, {"in1": Solution(a)})
+    s2 = Solution(r, {"in1": Solution(b)})
+
+    assert s1 != s2
+    assert s1.equals_ignoring_args(s2)
+    assert not s1.equals_ignoring_args(s1.args[0].solution)
+
+
+def test_solution_args_mapping():
+    r = Rule(example_fn, "test", str, {"in1": str, "in2": str}, 3, False)
+    a = Rule(example_fn, "a", Annotated[str, NameAttr("a")], {}, 0, False)
+    b = Rule(example_fn, "b", Annotated[str, NameAttr("b")], {}, 0, False)
+    args = Solution(r, {"in2": Solution(b), "in1": Solution(a)}).args
+
+    assert args.keys() == ["in1", "in2"]
+    assert args.values() == [Solution(a), Solution(b)]
+    assert args.items() == [("in1", Solution(a)), ("in2", Solution(b))]
+    assert args["in2"].solution == Solution(b)
+    assert args.get("in1").solution == Solution(a)
+    assert args.get("in3") is None
+    with pytest.raises(KeyError):
+        args["in3"]
+
+    assert args[0].name == "in1"
+    assert args[1].solution == Solution(b)
+    with pytest.raises(IndexError):
+        args[2]
+
+
+def test_solution_fingerprint():
+    def build(rule_name: str) -> Solution:
+        r = Rule(example_fn, "test", str, {"in1": str}, 3, False)
+        a = Rule(example_fn, rule_name, str, {}, 0, False)
+        return Solution(r, {"in1": Solution(a)})
+
+    fingerprint = build("a").fingerprint()
+    assert fingerprint == build("a").fingerprint()
+    assert fingerprint != build("b").fingerprint()
+    assert len(fingerprint) == 16
+
+
+def test_optional_type_info():
+    type_info = TypeInfo.parse(Optional[int])
+    assert type_info.optional
+    assert type_info.inner_type is int
+    assert not TypeInfo.parse(int).optional
+    assert type_info != TypeInfo.parse(int)
+    assert type_info == TypeInfo.parse(int | None)
+
+
+def test_annotated_optional_type_info():
+    type_info = TypeInfo.parse(Annotated[Optional[str], NameAttr("test")])
+    assert type_info.optional
+    assert type_info.inner_type is str
+    assert type_info.attributes == MetadataSet([NameAttr("test")])
+
+
+def test_dependency_from_type_info():
+    annotation = Annotated[
+        Optional[str], NameAttr("test"), SolveCardinality.Single
+    ]
+    type_info = TypeInfo.parse(annotation)
+    dependency = Dependency("name", type_info)
+
+    assert dependency.typing == type_info
+    assert repr(dependency.typing) == repr(type_info)
+    assert dependency.typing.solve_parameter == type_info.solve_parameter
+    assert dependency.optional
+    assert not dependency.variadic
+    assert dependency == Dependency("name", annotation)
+
+
+def test_solve_unsatisfiable_optional():
+    solver = Solver(RuleRegistry())
+    assert solver.solve_for(Optional[int]) == []
+
+
+def test_generic_type_args():
+    assert TypeInfo.parse(List[int]) != TypeInfo.parse(List[str])
+    assert TypeInfo.parse(List[int]) == TypeInfo.parse(List[int])
+    assert hash(TypeInfo.parse(List[int])) == hash(TypeInfo.parse(List[int]))
+    assert TypeInfo.parse(Dict[str, int]).type_args == (str, int)
+    assert TypeInfo.parse(Dict[str, int]) != TypeInfo.parse(Dict[str, str])
+
+
+def test_bare_generic_type_args():
+    type_info = TypeInfo.parse(list)
+    assert type_info.type_args == ()
+    assert type_info.inner_type is list
+    assert type_info != TypeInfo.parse(List[int])
+
+
+def test_registry_generic_type_args():
+    reg = RuleRegistry()
+    ints = Rule(example_fn, "ints", Dict[str, int], {}, 0, False)
+    strs = Rule(example_fn, "strs", Dict[str, str], {}, 0, False)
+    reg.add_rules([ints, strs])
+    assert reg.get_rules(Dict[str, int]) == (ints,)
+    assert reg.get_rules(Dict[str, str]) == (strs,)
+
+
+T = TypeVar("T")
+
+
+class Box(Generic[T]):
+    pass
+
+
+def test_colliding_type_args():
+    # Python hashes -1 like -2, so are the hashes of these arguments.
+    low, lower = Box[Literal[-1]], Box[Literal[-2]]
+    assert hash(low) == hash(lower)
+
+    assert TypeInfo.parse(low) != TypeInfo.parse(lower)
+    reg = RuleRegistry()
+    reg.add_rule(Rule(example_fn, "low", low, {}, 0, False))
+    assert reg.get_rules(lower) is None
+
+
+@dataclass(frozen=True)
+class ScopeAttr:
+    name: str
+
+
+@dataclass(frozen=True)
+class VersionAttr:
+    number: int
+
+
+def test_type_string_is_sorted():
+    t = TypeInfo.parse(
+        Annotated[str, VersionAttr(1), NameAttr("a"), ScopeAttr("b")]
+    )
+    rendered = str(t)
+    assert all(str(t) == rendered for _ in range(10))
+    assert rendered == (
+        "str(+x, NameAttr(name='a'), ScopeAttr(name='b'), "
+        "VersionAttr(number=1))"
+    )
+    reordered = TypeInfo.parse(
+        Annotated[str, ScopeAttr("b"), NameAttr("a"), VersionAttr(1)]
+    )
+    assert str(reordered) == rendered
+    assert str(t.attributes) == str(reordered.attributes)
@@ -2,8 +2,9 @@ from dataclasses import dataclass
 
 from pytest import raises
 
+from composify.core import TypeInfo
 from composify.errors import CyclicDependencyError, SolveFailureError
-from composify.rules import collect_rules, rule
+from composify.rules import as_rule, collect_rules, rule
 from tests.utils import create_rule_solver
 
 
@@ -49,3 +50,36 @@ def test_cyclic_dependency_but_okay():
     resolver = create_rule_solver(*rules_2)
     plans = list(resolver.solve_for(B))
     assert len(plans) > 0
+
+
+def test_cyclic_dependency_hint():
+    resolver = create_rule_solver(*rules)
+    with raises(SolveFailureError) as exc:
+        list(resolver.solve_for(B))
+    error = next(
+        e for e in exc.value.errors if isinstance(e, CyclicDependencyError)
+    )
+    assert error.hint == (
+        f"dependency 'param' of rule {as_rule(create_b).canonical_name} "
+        f"closes the cycle, register a rule providing {TypeInfo.parse(B)} "
+        "outside of the cycle to break it"
+    )
+    assert error.hint in str(error)
+
+
+def test_cyclic_dependency_path():
+    resolver = create_rule_solver(*rules)
+    with raises(SolveFailureError) as exc:
+        list(resolver.solve_for(B))
+    error = next(
+        e for e in exc.value.errors if isinstance(e, CyclicDependencyError)
+    )
+    assert [trace[1] for trace in error.cycle] == [
+        TypeInfo.parse(B),
+        TypeInfo.parse(A),
+        TypeInfo.parse(B),
+    ]
+    assert error.path == (
+        f"{TypeInfo.parse(B)} -> {TypeInfo.parse(A)} -> {TypeInfo.parse(B)}"
+    )
+    assert error.path in str(error)
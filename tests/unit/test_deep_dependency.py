@@ -0,0 +1,210 @@
+from typing import Annotated
+
+import pytest
+
+from composify.core import Rule, SolveCardinality
+from composify.errors import (
+    MaxDepthExceededError,
+    SolveFailureError,
+    SolveTimeoutError,
+)
+from tests.utils import create_rule_solver
+
+
+def init_level(self, prev=None):
+    self.prev = prev
+
+
+def create_chain(depth: int) -> tuple[list[type], list[Rule]]:
+    types = [
+        type(f"Level{i}", (), {"__init__": init_level}) for i in range(depth)
+    ]
+    rules = [Rule(types[0], "level_0", types[0], {}, 0, False)]
+    for i in range(1, depth):
+        rules.append(
+            Rule(
+                types[i],
+                f"level_{i}",
+                types[i],
+                {"prev": types[i - 1]},
+                0,
+                False,
+            )
+        )
+    return types, rules
+
+
+def test_deep_chain():
+    types, rules = create_chain(10)
+    solver = create_rule_solver(*rules)
+
+    (solution,) = solver.solve_for(types[-1])
+    depth = 0
+    while list(solution.args):
+        solution = solution.args[0].solution
+        depth += 1
+    assert depth == 9
+    assert solution.rule.canonical_name == "level_0"
+
+
+def test_solution_depth():
+    types, rules = create_chain(10)
+    solver = create_rule_solver(*rules)
+
+    (solution,) = solver.solve_for(types[-1])
+    assert solution.depth() == 10
+    assert solution.node_count() == 10
+    assert solution.node_count(unique=True) == 10
+
+
+def create_diamond(
+    depth: int, priority: int = 0
+) -> tuple[list[type], list[Rule]]:
+    types = [type(f"Diamond{i}", (), {}) for i in range(depth)]
+    rules = [Rule(types[0], "diamond_0", types[0], {}, 1, False)]
+    for i in range(1, depth):
+        rules.append(
+            Rule(
+                types[i],
+                f"diamond_{i}",
+                types[i],
+                {"left": types[i - 1], "right": types[i - 1]},
+                priority,
+                False,
+            )
+        )
+    return types, rules
+
+
+def test_diamond_depth_and_score():
+    types, rules = create_diamond(40)
+    solver = create_rule_solver(*rules)
+
+    (solution,) = solver.solve_for(types[-1])
+    assert solution.depth() == 40
+    assert solution.node_count(unique=True) == 40
+    assert solution.score() == 2**39
+
+
+def test_score_saturates():
+    types, rules = create_diamond(40, priority=2**31 - 1)
+    rules.append(
+        Rule(
+            types[-1],
+            "diamond_alternative",
+            types[-1],
+            {"left": types[-2], "right": types[-2]},
+            2**31 - 1,
+            False,
+        )
+    )
+    solver = create_rule_solver(*rules)
+
+    solutions = solver.solve_for(
+        Annotated[types[-1], SolveCardinality.Exhaustive]
+    )
+    assert [s.score() for s in solutions] == [2**63 - 1, 2**63 - 1]
+
+
+def test_max_depth_exceeded():
+    types, rules = create_chain(10)
+    solver = create_rule_solver(*rules, max_depth=5)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(types[-1])
+    assert exc.value.contains(MaxDepthExceededError)
+    error = next(
+        e for e in exc.value.errors if isinstance(e, MaxDepthExceededError)
+    )
+    assert len(error.traces) == 6
+
+
+def test_visit_budget_exceeded():
+    types, rules = create_chain(100)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(types[-1], max_visits=10)
+    assert exc.value.contains(SolveTimeoutError)
+    assert solver.memo_stats()["total"] == 0
+
+    assert len(solver.solve_for(types[-1], max_visits=100)) == 1
+
+
+def test_timeout_exceeded():
+    types, rules = create_chain(100)
+    solver = create_rule_solver(*rules)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(types[-1], timeout=0)
+    assert exc.value.contains(SolveTimeoutError)
+
+
+def test_huge_timeout():
+    types, rules = create_chain(10)
+    solver = create_rule_solver(*rules)
+
+    assert len(solver.solve_for(types[-1], timeout=1e19)) == 1
+
+
+def test_unbounded_depth():
+    types, rules = create_chain(100)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    assert len(solver.solve_for(types[-1])) == 1
+
+
+def test_very_deep_chain():
+    types, rules = create_chain(10_000)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    (solution,) = solver.solve_for(types[-1])
+    assert solution.rule.canonical_name == "level_9999"
+    assert solution.depth() == 10_000
+
+
+def test_very_deep_build_plan():
+    types, rules = create_chain(10_000)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    (solution,) = solver.solve_for(types[-1])
+    plan = solution.build_plan()
+    assert len(plan) == 10_000
+    assert plan[0].rule.canonical_name == "level_0"
+    assert plan[-1] == solution
+
+
+def test_very_deep_to_dict():
+    types, rules = create_chain(10_000)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    (solution,) = solver.solve_for(types[-1])
+    node = solution.to_dict()
+    for i in reversed(range(1, 10_000)):
+        assert node["canonical_name"] == f"level_{i}"
+        node = node["args"]["prev"]
+    assert node["canonical_name"] == "level_0"
+    assert node["args"] == {}
+
+
+def test_very_deep_build():
+    types, rules = create_chain(10_000)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    (solution,) = solver.solve_for(types[-1])
+    value = solution.build()
+    for i in reversed(range(1, 10_000)):
+        assert type(value) is types[i]
+        value = value.prev
+    assert type(value) is types[0]
+    assert value.prev is None
+
+
+def test_very_deep_fingerprint():
+    types, rules = create_chain(10_000)
+    solver = create_rule_solver(*rules, max_depth=None)
+
+    (solution,) = solver.solve_for(types[-1])
+    (again,) = solver.solve_for(types[-1])
+    assert solution.fingerprint() == again.fingerprint()
+    assert solution.fingerprint() != solution.args[0].solution.fingerprint()
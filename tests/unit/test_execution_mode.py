@@ -0,0 +1,73 @@
+from dataclasses import dataclass
+
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Service:
+    mode: str
+
+
+@dataclass(frozen=True)
+class Handler:
+    service: Service
+
+
+@dataclass(frozen=True)
+class Database:
+    mode: str
+
+
+@rule
+def create_service() -> Service:
+    return Service("sync")
+
+
+@rule
+async def create_service_async() -> Service:
+    return Service("async")
+
+
+@rule
+def create_handler(service: Service) -> Handler:
+    return Handler(service)
+
+
+@rule
+async def create_database_async() -> Database:
+    return Database("async")
+
+
+def test_sync_mode_selects_sync_variant():
+    solver = create_rule_solver(
+        create_service, create_service_async, create_handler
+    )
+
+    (handler,) = solver.solve_for(Handler)
+    assert handler.args[0].solution.rule == as_rule(create_service)
+    assert not handler.args[0].solution.is_async
+
+
+def test_async_mode_selects_async_variant():
+    solver = create_rule_solver(
+        create_service, create_service_async, create_handler
+    )
+
+    (handler,) = solver.solve_for_async(Handler)
+    assert handler.rule == as_rule(create_handler)
+    assert handler.args[0].solution.rule == as_rule(create_service_async)
+    assert handler.args[0].solution.is_async
+
+    (service,) = solver.solve_for(Service)
+    assert service.rule == as_rule(create_service)
+
+
+def test_single_variant_is_bridged():
+    solver = create_rule_solver(create_service, create_database_async)
+
+    (service,) = solver.solve_for_async(Service)
+    assert service.rule == as_rule(create_service)
+
+    (database,) = solver.solve_for(Database)
+    assert database.rule == as_rule(create_database_async)
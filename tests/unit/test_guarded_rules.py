@@ -0,0 +1,102 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+from composify.core import SolveCardinality, TypeInfo
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Cache:
+    name: str
+
+
+@dataclass(frozen=True)
+class WebRequest:
+    cache: Cache
+
+
+@dataclass(frozen=True)
+class Job:
+    cache: Cache
+
+
+def under_web_request(path: list[TypeInfo]) -> bool:
+    return any(t.inner_type is WebRequest for t in path)
+
+
+@rule(guard=under_web_request)
+def request_cache() -> Cache:
+    return Cache("request")
+
+
+@rule(guard=lambda path: not under_web_request(path))
+def default_cache() -> Cache:
+    return Cache("default")
+
+
+@rule
+def create_web_request(cache: Cache) -> WebRequest:
+    return WebRequest(cache)
+
+
+@rule
+def create_job(cache: Cache) -> Job:
+    return Job(cache)
+
+
+def test_guard_receives_resolution_path():
+    paths = []
+
+    @rule(guard=lambda path: paths.append(path) or True)
+    def guarded_cache() -> Cache:
+        return Cache("guarded")
+
+    solver = create_rule_solver(create_web_request, guarded_cache)
+    solver.solve_for(WebRequest)
+
+    assert [[t.inner_type for t in path] for path in paths] == [
+        [WebRequest, Cache]
+    ]
+
+
+def test_rule_chosen_under_ancestor():
+    solver = create_rule_solver(
+        request_cache, default_cache, create_web_request, create_job
+    )
+
+    (web_request,) = solver.solve_for(WebRequest)
+    assert web_request.args[0].solution.rule == as_rule(request_cache)
+
+    (job,) = solver.solve_for(Job)
+    assert job.args[0].solution.rule == as_rule(default_cache)
+
+    (cache,) = solver.solve_for(Cache)
+    assert cache.rule == as_rule(default_cache)
+
+
+def test_enabled_predicate_toggles_rule():
+    flags = {"redis": False}
+
+    @rule(enabled=lambda: flags["redis"])
+    def redis_cache() -> Cache:
+        return Cache("redis")
+
+    solver = create_rule_solver(redis_cache, default_cache)
+    every_cache = Annotated[Cache, SolveCardinality.Exhaustive]
+
+    solutions = solver.solve_for(every_cache)
+    assert [s.rule for s in solutions] == [as_rule(default_cache)]
+
+    flags["redis"] = True
+    solver.clear_cache()
+    solutions = solver.solve_for(every_cache)
+    assert {s.rule for s in solutions} == {
+        as_rule(redis_cache),
+        as_rule(default_cache),
+    }
+
+    flags["redis"] = False
+    solver.invalidate(every_cache)
+    solutions = solver.solve_for(every_cache)
+    assert [s.rule for s in solutions] == [as_rule(default_cache)]
@@ -3,9 +3,9 @@ from typing import Annotated
 
 import pytest
 
-from composify.core import SolveSpecificity
+from composify.core import RuleRegistry, SolveCardinality, SolveSpecificity
 from composify.errors import NoSolutionError, SolveFailureError
-from composify.rules import rule
+from composify.rules import as_rule, rule
 from tests.utils import create_rule_solver, solution
 
 
@@ -88,3 +88,154 @@ async def test_allowed_subclasses(compare_solutions):
         resolver.solve_for(C),
         [solution(create_c)],
     )
+
+
+@pytest.mark.asyncio_cooperative
+async def test_match_kind():
+    resolver = create_rule_solver(create_a, create_c)
+    (exact,) = resolver.solve_for(
+        Annotated[A, SolveSpecificity.Exact, SolveCardinality.Exhaustive]
+    )
+    assert exact.match_kind == SolveSpecificity.Exact
+
+    resolver = create_rule_solver(create_a, create_c)
+    subclasses = resolver.solve_for(
+        Annotated[A, SolveCardinality.Exhaustive]
+    )
+    assert {s.rule.canonical_name: s.match_kind for s in subclasses} == {
+        as_rule(create_a).canonical_name: SolveSpecificity.Exact,
+        as_rule(create_c).canonical_name: SolveSpecificity.AllowSubclass,
+    }
+
+    resolver = create_rule_solver(create_a, create_c)
+    superclasses = resolver.solve_for(
+        Annotated[
+            C, SolveSpecificity.AllowSuperclass, SolveCardinality.Exhaustive
+        ]
+    )
+    assert {s.rule.canonical_name: s.match_kind for s in superclasses} == {
+        as_rule(create_a).canonical_name: SolveSpecificity.AllowSuperclass,
+        as_rule(create_c).canonical_name: SolveSpecificity.Exact,
+    }
+
+    assert solution(create_a).match_kind is None
+
+
+@dataclass(frozen=True)
+class Base:
+    value: int
+
+
+@dataclass(frozen=True)
+class Mid(Base):
+    value: int
+
+
+@dataclass(frozen=True)
+class Leaf(Mid):
+    value: int
+
+
+@rule(priority=1)
+def create_base() -> Base:
+    return Base(1)
+
+
+@rule(priority=2)
+def create_mid() -> Mid:
+    return Mid(2)
+
+
+@rule(priority=3)
+def create_leaf() -> Leaf:
+    return Leaf(3)
+
+
+@pytest.mark.asyncio_cooperative
+async def test_subclass_specificity_order():
+    resolver = create_rule_solver(create_leaf, create_base, create_mid)
+
+    solutions = resolver.solve_for(
+        Annotated[Base, SolveCardinality.Exhaustive]
+    )
+    assert [s.rule for s in solutions] == [
+        as_rule(create_base),
+        as_rule(create_mid),
+        as_rule(create_leaf),
+    ]
+
+
+@pytest.mark.asyncio_cooperative
+async def test_superclass_specificity_order():
+    resolver = create_rule_solver(create_base, create_leaf, create_mid)
+
+    solutions = resolver.solve_for(
+        Annotated[
+            Leaf, SolveSpecificity.AllowSuperclass, SolveCardinality.Exhaustive
+        ]
+    )
+    assert [s.rule for s in solutions] == [
+        as_rule(create_leaf),
+        as_rule(create_mid),
+        as_rule(create_base),
+    ]
+
+
+def test_subclass_or_superclass_specificity():
+    resolver = create_rule_solver(create_base, create_leaf, create_mid)
+
+    solutions = resolver.solve_for(
+        Annotated[
+            Mid,
+            SolveSpecificity.AllowSubclassOrSuperclass,
+            SolveCardinality.Exhaustive,
+        ]
+    )
+    assert [s.rule for s in solutions] == [
+        as_rule(create_mid),
+        as_rule(create_leaf),
+        as_rule(create_base),
+    ]
+
+
+def test_subclass_or_superclass_symbol():
+    specificity = SolveSpecificity.AllowSubclassOrSuperclass
+    assert str(specificity) == "~"
+    assert repr(specificity) == "AllowSubclassOrSuperclass"
+
+
+def test_specificity_distance():
+    registry = RuleRegistry()
+    registry.add_rules(
+        as_rule(r) for r in (create_base, create_mid, create_leaf)
+    )
+
+    assert registry.distance(Base, Base) == 0
+    assert registry.distance(Base, Leaf) == 2
+    assert registry.distance(Leaf, Mid) == 1
+    assert registry.distance(Leaf, B) is None
+
+
+def test_is_assignable():
+    registry = RuleRegistry()
+    exact = SolveSpecificity.Exact
+    sub = SolveSpecificity.AllowSubclass
+    sup = SolveSpecificity.AllowSuperclass
+
+    assert registry.is_assignable(A, A, exact)
+    assert not registry.is_assignable(C, A, exact)
+
+    assert registry.is_assignable(C, A, sub)
+    assert not registry.is_assignable(A, C, sub)
+    assert registry.is_assignable(Leaf, Base)
+
+    assert registry.is_assignable(A, C, sup)
+    assert not registry.is_assignable(C, A, sup)
+    assert registry.is_assignable(A, Annotated[C, sup])
+
+    either = SolveSpecificity.AllowSubclassOrSuperclass
+    assert registry.is_assignable(A, C, either)
+    assert registry.is_assignable(C, A, either)
+
+    assert not registry.is_assignable(B, A, either)
+    assert not registry.is_assignable(A, B)
@@ -0,0 +1,128 @@
+from dataclasses import dataclass
+from functools import partial
+from typing import Annotated
+
+import pytest
+
+from composify.core import (
+    Dependencies,
+    RuleRegistry,
+    Solver,
+    TypeInfo,
+    make_rule,
+)
+from composify.errors import (
+    MissingParameterTypeAnnotation,
+    MissingReturnTypeAnnotation,
+)
+from composify.rules import static_rule
+
+
+@dataclass(frozen=True)
+class Param:
+    value: str
+
+
+@dataclass(frozen=True)
+class Result:
+    value: str
+
+
+def create_result(param: Param, suffix: Annotated[str, "suffix"]) -> Result:
+    return Result(param.value + suffix)
+
+
+async def create_result_async(param: Param) -> Result:
+    return Result(param.value)
+
+
+class Factory:
+    def __init__(self, suffix: str):
+        self.suffix = suffix
+
+    def create(self, param: Param) -> Result:
+        return Result(param.value + self.suffix)
+
+
+def test_make_rule_from_function():
+    derived = make_rule(create_result, priority=3)
+
+    assert derived.canonical_name == f"{__name__}.create_result"
+    assert derived.output_type == TypeInfo(Result)
+    assert derived.dependencies == Dependencies(
+        {"param": Param, "suffix": Annotated[str, "suffix"]}
+    )
+    assert derived.priority == 3
+    assert not derived.is_async
+
+
+def test_make_rule_async():
+    derived = make_rule(create_result_async, canonical_name="custom")
+
+    assert derived.canonical_name == "custom"
+    assert derived.is_async
+
+
+def test_make_rule_from_class():
+    derived = make_rule(Result)
+
+    assert derived.output_type == TypeInfo(Result)
+    assert derived.dependencies == Dependencies({"value": str})
+
+
+def test_make_rule_solvable():
+    registry = RuleRegistry()
+    registry.add_rule(make_rule(create_result_async))
+    registry.add_rule(static_rule("param", Param("value")))
+
+    solutions = Solver(registry).solve_for(Result)
+    assert [s.rule.canonical_name for s in solutions] == [
+        f"{__name__}.create_result_async"
+    ]
+
+
+def test_make_rule_from_partial():
+    derived = make_rule(partial(create_result, suffix="!"))
+
+    assert derived.canonical_name == f"{__name__}.create_result"
+    assert derived.dependencies == Dependencies({"param": Param})
+
+    registry = RuleRegistry()
+    registry.add_rule(derived)
+    registry.add_rule(static_rule("param", Param("value")))
+    (solution,) = Solver(registry).solve_for(Result)
+    assert solution.rule.function(Param("value")) == Result("value!")
+
+
+def test_make_rule_from_bound_method():
+    derived = make_rule(Factory("?").create)
+
+    assert derived.canonical_name == f"{__name__}.Factory.create"
+    assert derived.output_type == TypeInfo(Result)
+    assert derived.dependencies == Dependencies({"param": Param})
+
+
+def test_make_rule_missing_annotations():
+    def no_return(param: Param):
+        pass
+
+    def no_param(param) -> Result:
+        pass
+
+    with pytest.raises(MissingReturnTypeAnnotation):
+        make_rule(no_return)
+    with pytest.raises(MissingParameterTypeAnnotation):
+        make_rule(no_param)
+
+
+def test_dependencies_lookup():
+    dependencies = Dependencies({"value": str, "count": int, "name": str})
+
+    assert len(dependencies) == 3
+    assert "count" in dependencies
+    assert "missing" not in dependencies
+    assert dependencies["count"].typing == TypeInfo.parse(int)
+    assert dependencies.get("name").name == "name"
+    assert dependencies.get("missing") is None
+    with pytest.raises(KeyError):
+        dependencies["missing"]
@@ -0,0 +1,227 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.core import MetadataSet, Rule, TypeInfo
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Tag:
+    name: str
+
+
+@dataclass(frozen=True)
+class Scope:
+    name: str
+
+
+def test_subset_compares_values():
+    a = MetadataSet([Tag("a")])
+    b = MetadataSet([Tag("b")])
+
+    assert not a.issubset(b)
+    assert not a.issuperset(b)
+    assert a != b
+
+
+def test_subset_with_equal_values():
+    a = MetadataSet([Tag("a")])
+    ab = MetadataSet([Tag("a"), Scope("b")])
+
+    assert a.issubset(ab)
+    assert ab.issuperset(a)
+    assert not ab.issubset(a)
+    assert a == MetadataSet([Tag("a")])
+
+
+class Incomparable:
+    def __hash__(self) -> int:
+        return 0
+
+    def __eq__(self, other: object) -> bool:
+        raise ValueError("not comparable")
+
+
+def test_equality_raises_if_values_raise():
+    with pytest.raises(ValueError):
+        MetadataSet([Incomparable()]) == MetadataSet([Incomparable()])
+
+    assert MetadataSet([Incomparable()]) != MetadataSet([Tag("a")])
+    assert MetadataSet([Tag("a")]) != "a"
+
+
+@dataclass(frozen=True)
+class Level:
+    value: int
+
+
+class Leveled:
+    pass
+
+
+def test_colliding_hashes_are_compared_by_value():
+    # Python hashes -1 like -2, so are the hashes of these tags.
+    assert hash(Level(-1)) == hash(Level(-2))
+    low = Annotated[Leveled, Level(-1)]
+    lower = Annotated[Leveled, Level(-2)]
+
+    assert MetadataSet([Level(-1)]) != MetadataSet([Level(-2)])
+    assert TypeInfo.parse(low) != TypeInfo.parse(lower)
+
+    solver = create_rule_solver(
+        Rule(Leveled, "low", low, {}, 0, False),
+        Rule(Leveled, "lower", lower, {}, 0, False),
+    )
+    assert solver.solve_for(lower)[0].rule.canonical_name == "lower"
+    assert solver.solve_for(low)[0].rule.canonical_name == "low"
+
+
+def test_multiple_values_of_same_type():
+    tags = MetadataSet([Tag("a"), Tag("b")])
+
+    assert tags.get(Tag) == Tag("a")
+    assert tags[Tag] == Tag("a")
+    assert tags.get_all(Tag) == [Tag("a"), Tag("b")]
+    assert tags.get_all(Scope) == []
+    assert sorted(t.name for t in tags) == ["a", "b"]
+
+
+def test_multiple_values_subset():
+    a = MetadataSet([Tag("a")])
+    b = MetadataSet([Tag("b")])
+    ab = MetadataSet([Tag("a"), Tag("b")])
+
+    assert a.issubset(ab)
+    assert b.issubset(ab)
+    assert not ab.issubset(a)
+    assert ab.issuperset(b)
+    assert not MetadataSet([Tag("c")]).issubset(ab)
+
+
+def test_single_valued():
+    tags = MetadataSet([Tag("a"), Tag("b")], single_valued=True)
+
+    assert tags.get_all(Tag) == [Tag("b")]
+    assert tags == MetadataSet([Tag("b")])
+
+
+def test_order_independent():
+    ab = MetadataSet([Tag("a"), Scope("b"), Tag("c")])
+    ba = MetadataSet([Scope("b"), Tag("c"), Tag("a")])
+
+    assert ab == ba
+    assert hash(ab) == hash(ba)
+
+
+def test_duplicate_values_multiplicity():
+    a = MetadataSet([Tag("a")])
+    aa = MetadataSet([Tag("a"), Tag("a")])
+    aab = MetadataSet([Tag("a"), Tag("b"), Tag("a")])
+
+    assert aa.get_all(Tag) == [Tag("a"), Tag("a")]
+    assert a.issubset(aa)
+    assert not aa.issubset(a)
+    assert aa.issubset(aab)
+    assert aab.issuperset(aa)
+    assert aa != a
+    assert aab == MetadataSet([Tag("b"), Tag("a"), Tag("a")])
+
+
+def test_set_operations():
+    a = MetadataSet([Tag("a"), Scope("x")])
+    b = MetadataSet([Tag("b"), Scope("x")])
+
+    assert a.union(b) == MetadataSet([Tag("a"), Tag("b"), Scope("x")])
+    assert a.intersection(b) == MetadataSet([Scope("x")])
+    assert a.difference(b) == MetadataSet([Tag("a")])
+    assert hash(a.union(b)) == hash(b.union(a))
+
+
+def test_set_operation_identities():
+    a = MetadataSet([Tag("a"), Tag("c"), Scope("x")])
+    b = MetadataSet([Tag("a"), Tag("b")])
+
+    assert a.union(b).issuperset(a)
+    assert a.union(b).issuperset(b)
+    assert a.intersection(b).issubset(a)
+    assert a.intersection(b).issubset(b)
+    assert a.difference(b).union(a.intersection(b)) == a
+    assert a.difference(a) == MetadataSet([])
+    assert a.union(MetadataSet([])) == a
+
+
+def test_set_operations_multiplicity():
+    aa = MetadataSet([Tag("a"), Tag("a")])
+    ab = MetadataSet([Tag("a"), Tag("b")])
+
+    assert aa.union(ab) == MetadataSet([Tag("a"), Tag("a"), Tag("b")])
+    assert aa.intersection(ab) == MetadataSet([Tag("a")])
+    assert aa.difference(ab) == MetadataSet([Tag("a")])
+
+
+class CountingEq:
+    def __init__(self) -> None:
+        self.comparisons = 0
+
+    def __hash__(self) -> int:
+        return 0
+
+    def __eq__(self, other: object) -> bool:
+        self.comparisons += 1
+        return self is other
+
+
+def test_clones_share_storage():
+    value = CountingEq()
+    type_info = TypeInfo.parse(Annotated[int, value])
+
+    # Each access returns a clone sharing the storage of the same set.
+    assert type_info.attributes == type_info.attributes
+    assert value.comparisons == 0
+
+    assert type_info.attributes == MetadataSet([value])
+    assert value.comparisons > 0
+
+
+class Unhashable:
+    __hash__ = None
+
+    def __repr__(self) -> str:
+        return "Unhashable()"
+
+
+def test_unhashable_metadata_error():
+    with pytest.raises(TypeError, match=r"Unhashable\(\) at index 1"):
+        TypeInfo.parse(Annotated[int, "first", Unhashable()])
+
+    with pytest.raises(TypeError, match=r"Unhashable\(\) at index 0"):
+        MetadataSet([Unhashable()])
+
+
+def test_filter():
+    metadata = MetadataSet([Tag("a"), Scope("b"), Tag("c")])
+
+    scopes = metadata.filter(lambda value: isinstance(value, Scope))
+    assert list(scopes) == [Scope("b")]
+    assert scopes[Scope] == Scope("b")
+    assert scopes.get(Tag) is None
+    assert scopes == MetadataSet([Scope("b")])
+    assert hash(scopes) == hash(MetadataSet([Scope("b")]))
+
+
+def test_contains_and_len():
+    empty = MetadataSet([])
+    assert len(empty) == 0
+    assert Tag not in empty
+
+    metadata = MetadataSet([Tag("a"), Scope("b"), Tag("c")])
+    assert len(metadata) == 3
+    assert Tag in metadata
+    assert Scope in metadata
+    assert str not in metadata
+    assert Tag("a") not in metadata
+
+    single = MetadataSet([Tag("a"), Tag("c")], single_valued=True)
+    assert len(single) == 1
@@ -0,0 +1,88 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+from composify.builder import Builder
+from composify.core import SolveCardinality
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver, solution
+
+
+@dataclass(frozen=True)
+class Database:
+    url: str
+
+
+@dataclass(frozen=True)
+class Service:
+    database: Database
+
+
+@rule
+def create_database() -> Database:
+    return Database("postgres://production")
+
+
+@rule
+def create_test_database() -> Database:
+    return Database("sqlite://")
+
+
+@rule
+def create_service(database: Database) -> Service:
+    return Service(database)
+
+
+def test_mock_object():
+    solver = create_rule_solver(create_database, create_service)
+    fake = Database("fake://")
+
+    (service,) = solver.solve_for(Service, mocks={Database: fake})
+    assert Builder().from_solution(service).database is fake
+
+    (service,) = solver.solve_for(Service)
+    assert service.args[0].solution.rule == as_rule(create_database)
+
+
+def test_mock_rule():
+    solver = create_rule_solver(create_database, create_service)
+
+    (service,) = solver.solve_for(
+        Service, mocks={Database: as_rule(create_test_database)}
+    )
+    assert service.args[0].solution.rule == as_rule(create_test_database)
+
+
+@rule
+def primary_url() -> str:
+    return "primary://"
+
+
+@rule(priority=-1)
+def replica_url() -> str:
+    return "replica://"
+
+
+@rule
+def create_database_from_any_url(
+    url: Annotated[str, SolveCardinality.Exhaustive],
+) -> Database:
+    return Database(url)
+
+
+def test_mock_rule_single_solution():
+    solver = create_rule_solver(primary_url, replica_url, create_service)
+    mocks = {Database: as_rule(create_database_from_any_url)}
+    every_database = Annotated[Database, SolveCardinality.Exhaustive]
+
+    (database,) = solver.solve_for(every_database, mocks=mocks)
+    assert Builder().from_solution(database).url == "primary://"
+    (service,) = solver.solve_for(Service, mocks=mocks)
+    assert Builder().from_solution(service).database.url == "primary://"
+
+def test_mock_solution():
+    solver = create_rule_solver(create_service)
+
+    (service,) = solver.solve_for(
+        Service, mocks={Database: solution(create_test_database)}
+    )
+    assert service.args[0].solution.rule == as_rule(create_test_database)
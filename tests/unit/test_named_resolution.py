@@ -57,3 +57,41 @@ async def test_multiple_with_named(compare_solutions):
             solution(create_special),
         ],
     )
+
+
+@pytest.mark.asyncio_cooperative
+async def test_prefer_attributes(compare_solutions):
+    solver = create_rule_solver(*rules_2)
+
+    compare_solutions(
+        solver.solve_for(
+            Annotated[A, SolveCardinality.Exhaustive],
+            prefer_attributes=["special"],
+        ),
+        [
+            solution(create_special),
+            solution(create_a),
+        ],
+    )
+
+    compare_solutions(
+        solver.solve_for(
+            Annotated[A, SolveCardinality.Single],
+            prefer_attributes=["special"],
+        ),
+        [
+            solution(create_special),
+        ],
+    )
+
+
+@pytest.mark.asyncio_cooperative
+async def test_prefer_attributes_fallback(compare_solutions):
+    solver = create_rule_solver(*rules_1)
+
+    compare_solutions(
+        solver.solve_for(A, prefer_attributes=["special"]),
+        [
+            solution(create_a),
+        ],
+    )
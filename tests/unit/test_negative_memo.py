@@ -0,0 +1,125 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.core import MetadataSet, RuleRegistry, Solver
+from composify.errors import SolveFailureError
+from composify.rules import as_rule, rule
+
+
+@dataclass(frozen=True)
+class Missing:
+    value: int
+
+
+@dataclass(frozen=True)
+class Target:
+    value: int
+
+
+class CountingQualifier:
+    def __init__(self) -> None:
+        self.calls = 0
+
+    def qualify(self, attributes: MetadataSet) -> bool:
+        self.calls += 1
+        return False
+
+
+counting_qualifier = CountingQualifier()
+
+
+@rule
+def create_missing() -> Missing:
+    return Missing(0)
+
+
+@rule
+def target_from_left(
+    left: Annotated[Missing, counting_qualifier],
+) -> Target:
+    return Target(left.value)
+
+
+@rule
+def target_from_right(
+    right: Annotated[Missing, counting_qualifier],
+) -> Target:
+    return Target(right.value)
+
+
+def test_unsolvable_type_is_solved_once():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_missing))
+    registry.add_rule(as_rule(target_from_left))
+    registry.add_rule(as_rule(target_from_right))
+    solver = Solver(registry)
+
+    counting_qualifier.calls = 0
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(Target)
+    # Solved once, the second rule hits the cached failure. Estimating the
+    # candidates evaluates no qualifiers.
+    assert counting_qualifier.calls == 1
+
+    # Failures are not shared between solves.
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(Target)
+    assert counting_qualifier.calls == 2
+
+
+@dataclass(frozen=True)
+class A:
+    value: int
+
+
+@dataclass(frozen=True)
+class B:
+    value: int
+
+
+@dataclass(frozen=True)
+class Top:
+    value: int
+
+
+@rule
+def a_from_b(b: B) -> A:
+    return A(b.value)
+
+
+@rule
+def default_a() -> A:
+    return A(1)
+
+
+@rule
+def b_from_a(a: A) -> B:
+    return B(a.value)
+
+
+@rule
+def b_from_missing(missing: Missing) -> B:
+    return B(missing.value)
+
+
+@rule
+def create_top(a: A, b: B) -> Top:
+    return Top(a.value + b.value)
+
+
+def test_cyclic_failure_not_cached():
+    registry = RuleRegistry()
+    registry.add_rules(
+        as_rule(r)
+        for r in (a_from_b, default_a, b_from_a, b_from_missing, create_top)
+    )
+    solver = Solver(registry)
+
+    # B is unsolvable while A is being solved (cycle), but solvable from Top.
+    solutions = solver.solve_for(Top)
+    assert len(solutions) == 1
+    [a, b] = sorted(solutions[0].args, key=lambda arg: arg.name)
+    assert a.solution.rule == as_rule(default_a)
+    assert b.solution.rule == as_rule(b_from_a)
@@ -0,0 +1,67 @@
+from dataclasses import dataclass
+from typing import Annotated, Optional
+
+from composify.builder import Builder
+from composify.core import DependencyMarker
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Config:
+    value: int
+
+
+@dataclass(frozen=True)
+class Service:
+    config: Config | None
+
+
+@rule
+def default_config() -> Config:
+    return Config(1)
+
+
+@rule
+def create_service(config: Optional[Config] = None) -> Service:
+    return Service(config)
+
+
+@rule
+def create_marked_service(
+    config: Annotated[Config, DependencyMarker.Optional] = Config(0),
+) -> Service:
+    return Service(config)
+
+
+def test_optional_dependency():
+    [dependency] = as_rule(create_service).dependencies
+    assert dependency.optional
+    [dependency] = as_rule(create_marked_service).dependencies
+    assert dependency.optional
+    assert not dependency.typing.optional
+
+
+def test_optional_dependency_without_provider():
+    solver = create_rule_solver(create_service)
+
+    [solution] = solver.solve_for(Service)
+
+    assert list(solution.args) == []
+    assert Builder().from_solution(solution) == Service(None)
+
+
+def test_optional_dependency_with_provider():
+    solver = create_rule_solver(create_service, default_config)
+
+    [solution] = solver.solve_for(Service)
+
+    assert Builder().from_solution(solution) == Service(Config(1))
+
+
+def test_marked_optional_dependency_without_provider():
+    solver = create_rule_solver(create_marked_service)
+
+    [solution] = solver.solve_for(Service)
+
+    assert Builder().from_solution(solution) == Service(Config(0))
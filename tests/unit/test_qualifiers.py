@@ -0,0 +1,265 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.core import (
+    MetadataSet,
+    Not,
+    QualifierMode,
+    Qualifiers,
+    RuleRegistry,
+    SolveCardinality,
+    Solver,
+    TypeInfo,
+)
+from composify.errors import NoSolutionError, SolveFailureError
+from composify.rules import as_rule, rule
+from tests.utils import create_rule_solver
+
+
+@dataclass(frozen=True)
+class Region:
+    name: str
+
+
+@dataclass(frozen=True)
+class InRegion:
+    name: str
+
+    def qualify(self, attributes: MetadataSet) -> bool:
+        return Region(self.name) in attributes.get_all(Region)
+
+
+@dataclass(frozen=True)
+class Database:
+    region: str
+
+
+@rule
+def create_eu() -> Annotated[Database, Region("eu")]:
+    return Database("eu")
+
+
+@rule
+def create_us() -> Annotated[Database, Region("us")]:
+    return Database("us")
+
+
+@rule
+def create_asia() -> Annotated[Database, Region("asia")]:
+    return Database("asia")
+
+
+database_rules = (create_eu, create_us, create_asia)
+
+
+def test_any_mode():
+    either = Qualifiers.any([InRegion("eu"), InRegion("us")])
+
+    assert either.mode == QualifierMode.Any
+    assert either.qualify(MetadataSet([Region("us")]))
+    assert not either.qualify(MetadataSet([Region("asia")]))
+    assert not Qualifiers.all([InRegion("eu"), InRegion("us")]).qualify(
+        MetadataSet([Region("us")])
+    )
+    assert hash(either) != hash(Qualifiers([InRegion("eu"), InRegion("us")]))
+    assert repr(either) == "(InRegion(name='eu') | InRegion(name='us'))"
+
+
+def test_qualifier_operators():
+    eu = Qualifiers([InRegion("eu")])
+    us = Qualifiers([InRegion("us")])
+    both = MetadataSet([Region("eu"), Region("us")])
+    only_us = MetadataSet([Region("us")])
+    asia = MetadataSet([Region("asia")])
+
+    conjunction = eu & us
+    assert conjunction == Qualifiers.all([InRegion("eu"), InRegion("us")])
+    assert hash(conjunction) == hash(
+        Qualifiers.all([InRegion("eu"), InRegion("us")])
+    )
+    assert conjunction.qualify(both)
+    assert not conjunction.qualify(only_us)
+
+    disjunction = eu | us
+    assert disjunction.mode == QualifierMode.Any
+    assert disjunction == Qualifiers.any([InRegion("eu"), InRegion("us")])
+    assert disjunction.qualify(only_us)
+    assert not disjunction.qualify(asia)
+
+    nested = disjunction & Not(InRegion("eu"))
+    assert repr(nested) == (
+        "((InRegion(name='eu') | InRegion(name='us')), !InRegion(name='eu'))"
+    )
+    assert nested.qualify(only_us)
+    assert not nested.qualify(both)
+    assert (eu | InRegion("asia")).qualify(asia)
+
+
+def test_solve_any_qualifiers():
+    either = Qualifiers.any([InRegion("eu"), InRegion("us")])
+    solver = create_rule_solver(*database_rules)
+
+    solutions = solver.solve_for(
+        Annotated[Database, either, SolveCardinality.Exhaustive]
+    )
+    regions = sorted(
+        s.rule.output_type.attributes[Region].name for s in solutions
+    )
+    assert regions == ["eu", "us"]
+
+
+def test_solve_any_qualifiers_single_match():
+    either = Qualifiers.any([InRegion("eu"), InRegion("moon")])
+    solver = create_rule_solver(*database_rules)
+
+    (solution,) = solver.solve_for(Annotated[Database, either])
+    assert solution.rule.output_type.attributes[Region] == Region("eu")
+
+    neither = Qualifiers.any([InRegion("mars"), InRegion("moon")])
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(Annotated[Database, neither])
+
+
+def test_not_qualifier():
+    not_eu = Not(InRegion("eu"))
+
+    assert not not_eu.qualify(MetadataSet([Region("eu")]))
+    assert not_eu.qualify(MetadataSet([Region("us")]))
+    assert not_eu == Not(InRegion("eu"))
+    assert hash(not_eu) == hash(Not(InRegion("eu")))
+    assert repr(not_eu) == "!InRegion(name='eu')"
+
+
+def test_solve_not_qualifier():
+    solver = create_rule_solver(*database_rules)
+
+    solutions = solver.solve_for(
+        Annotated[
+            Database,
+            Not(InRegion("eu")),
+            Not(InRegion("asia")),
+            SolveCardinality.Exhaustive,
+        ]
+    )
+    assert [s.rule.output_type.attributes[Region] for s in solutions] == [
+        Region("us")
+    ]
+
+
+def test_not_composes_with_any():
+    either = Qualifiers.any([InRegion("eu"), InRegion("us")])
+    solver = create_rule_solver(*database_rules)
+
+    (solution,) = solver.solve_for(Annotated[Database, Not(either)])
+    assert solution.rule.output_type.attributes[Region] == Region("asia")
+
+
+@dataclass(frozen=True)
+class CountingInRegion:
+    name: str
+    calls: list
+
+    def __hash__(self) -> int:
+        return hash(self.name)
+
+    def qualify(self, attributes: MetadataSet) -> bool:
+        self.calls.append(attributes)
+        return Region(self.name) in attributes.get_all(Region)
+
+
+def test_qualifier_cache():
+    def count_calls(cache_qualifiers):
+        calls = []
+        registry = RuleRegistry(cache_qualifiers=cache_qualifiers)
+        for r in (create_eu, create_us, create_asia):
+            registry.add_rule(as_rule(r))
+        target = Annotated[Database, CountingInRegion("eu", calls)]
+        for _ in range(3):
+            (solution,) = Solver(registry).solve_for(target)
+            assert solution.rule == as_rule(create_eu)
+        return len(calls)
+
+    assert count_calls(cache_qualifiers=False) == 9
+    assert count_calls(cache_qualifiers=True) == 3
+
+
+@dataclass(frozen=True)
+class Cache:
+    region: str
+
+
+@rule
+def create_eu_cache() -> Annotated[Cache, Region("eu")]:
+    return Cache("eu")
+
+
+@rule
+def create_us_cache() -> Annotated[Cache, Region("us")]:
+    return Cache("us")
+
+
+HOME_REGIONS = {Database: "asia", Cache: "us"}
+
+
+@dataclass(frozen=True)
+class InHomeRegion:
+    def qualify_for(
+        self, attributes: MetadataSet, requested: TypeInfo | None
+    ) -> bool:
+        region = Region(HOME_REGIONS[requested.inner_type])
+        return region in attributes.get_all(Region)
+
+
+def test_contextual_qualifier():
+    solver = create_rule_solver(
+        *database_rules, create_eu_cache, create_us_cache
+    )
+
+    (database,) = solver.solve_for(Annotated[Database, InHomeRegion()])
+    assert database.rule == as_rule(create_asia)
+    (cache,) = solver.solve_for(Annotated[Cache, InHomeRegion()])
+    assert cache.rule == as_rule(create_us_cache)
+    (cache,) = solver.solve_for(Annotated[Cache, Not(InHomeRegion())])
+    assert cache.rule == as_rule(create_eu_cache)
+
+    qualifiers = Qualifiers([InHomeRegion()])
+    attributes = MetadataSet([Region("asia")])
+    assert qualifiers.qualify(attributes, TypeInfo.parse(Database))
+    assert not qualifiers.qualify(attributes, TypeInfo.parse(Cache))
+
+
+@dataclass(frozen=True)
+class OnlyRegion:
+    name: str
+
+    def qualify(self, attributes: MetadataSet) -> bool:
+        return attributes.get_all(Region) == [Region(self.name)]
+
+    def rejection_reason(self, attributes: MetadataSet) -> str:
+        return f"{attributes[Region].name} is not {self.name}"
+
+
+def test_qualifier_rejection_diagnostics():
+    solver = create_rule_solver(create_eu, create_us)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(Annotated[Database, InRegion("asia")])
+    (error,) = exc.value.errors
+    assert isinstance(error, NoSolutionError)
+    assert sorted(reason for _, reason in error.rejections) == [
+        "qualifier InRegion(name='asia')",
+        "qualifier InRegion(name='asia')",
+    ]
+    assert "create_eu rejected by qualifier InRegion" in str(error)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(
+            Annotated[Database, Not(InRegion("eu")), OnlyRegion("eu")]
+        )
+    (error,) = exc.value.errors
+    assert sorted(reason for _, reason in error.rejections) == [
+        "qualifier !InRegion(name='eu')",
+        "us is not eu",
+    ]
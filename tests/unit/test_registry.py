@@ -0,0 +1,382 @@
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.core import (
+    OnDuplicate,
+    RuleRegistry,
+    SolveCardinality,
+    SolveSpecificity,
+    Solver,
+)
+from composify.errors import DuplicateRuleError
+from composify.rules import as_rule, rule
+
+
+@dataclass(frozen=True)
+class Base:
+    value: int
+
+
+@dataclass(frozen=True)
+class Derived(Base):
+    value: int
+
+
+@rule
+def create_base() -> Base:
+    return Base(1)
+
+
+@rule(priority=1)
+def create_other_base() -> Base:
+    return Base(2)
+
+
+@rule
+def create_derived() -> Derived:
+    return Derived(3)
+
+
+def test_remove_rule():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_other_base))
+
+    assert registry.remove_rule(as_rule(create_other_base))
+    assert registry.get_rules(Base) == (as_rule(create_base),)
+
+    assert not registry.remove_rule(as_rule(create_other_base))
+    assert registry.remove_rule(as_rule(create_base))
+    assert registry.get_rules(Base) is None
+
+
+def test_remove_last_rule_forgets_subclass():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_derived))
+    assert registry.distance(Base, Derived) == 1
+
+    registry.remove_rule(as_rule(create_derived))
+
+    assert registry.distance(Base, Derived) is None
+    assert registry.get_rules(Base) == (as_rule(create_base),)
+
+
+def test_clear():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_derived))
+
+    registry.clear()
+
+    assert registry.get_rules(Base) is None
+    assert registry.get_rules(Derived) is None
+    assert registry.distance(Base, Derived) is None
+
+
+def test_shared_hierarchy():
+    # A binary tree of classes, each registered after its base.
+    nodes = [type("Node0", (), {})]
+    for i in range(1, 50):
+        nodes.append(type(f"Node{i}", (nodes[(i - 1) // 2],), {}))
+
+    registry = RuleRegistry()
+    for node in nodes:
+
+        @rule
+        def create() -> node:
+            raise NotImplementedError
+
+        registry.add_rule(as_rule(create))
+
+    def depth(i):
+        return 0 if i == 0 else depth((i - 1) // 2) + 1
+
+    def size(i):
+        return 1 + sum(size(c) for c in (2 * i + 1, 2 * i + 2) if c < 50)
+
+    for i, node in enumerate(nodes):
+        assert registry.distance(nodes[0], node) == depth(i)
+        subclassed = Annotated[node, SolveSpecificity.AllowSubclass]
+        assert len(registry.get_rules(subclassed)) == size(i)
+
+
+def test_reindex():
+    class Late:
+        pass
+
+    class Placeholder:
+        pass
+
+    class LateDerived(Placeholder):
+        pass
+
+    @rule
+    def create_late() -> Late:
+        return Late()
+
+    @rule
+    def create_late_derived() -> LateDerived:
+        return LateDerived()
+
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_late))
+    registry.add_rule(as_rule(create_late_derived))
+    LateDerived.__bases__ = (Late,)
+    assert registry.distance(Late, LateDerived) is None
+
+    registry.reindex()
+
+    assert registry.distance(Late, LateDerived) == 1
+    subclassed = Annotated[Late, SolveSpecificity.AllowSubclass]
+    assert len(registry.get_rules(subclassed)) == 2
+
+
+def test_duplicate_rule_ignored():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_base))
+
+    assert len(registry) == 1
+    solutions = Solver(registry).solve_for(
+        Annotated[Base, SolveCardinality.Exhaustive]
+    )
+    assert [s.rule for s in solutions] == [as_rule(create_base)]
+
+
+def test_distinct_rules_sharing_a_name():
+    def make_rule(value):
+        @rule
+        def create() -> Base:
+            return Base(value)
+
+        return as_rule(create)
+
+    registry = RuleRegistry()
+    for value in range(3):
+        registry.add_rule(make_rule(value))
+
+    assert len(registry) == 3
+
+
+def test_duplicate_rule_error():
+    registry = RuleRegistry(on_duplicate=OnDuplicate.Error)
+    registry.add_rule(as_rule(create_base))
+
+    with pytest.raises(DuplicateRuleError, match="create_base"):
+        registry.add_rule(as_rule(create_base))
+    assert len(registry) == 1
+
+    registry = RuleRegistry(on_duplicate=OnDuplicate.Allow)
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_base))
+    assert len(registry) == 2
+
+
+def test_merge():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    other = RuleRegistry()
+    other.add_rule(as_rule(create_base))
+    other.add_rule(as_rule(create_derived))
+
+    registry.merge(other)
+    assert len(registry) == 2
+    subclassed = Annotated[Base, SolveSpecificity.AllowSubclass]
+    assert len(registry.get_rules(subclassed)) == 2
+
+    strict = RuleRegistry(on_duplicate=OnDuplicate.Error)
+    strict.add_rule(as_rule(create_derived))
+    with pytest.raises(DuplicateRuleError, match="create_derived"):
+        strict.merge(other)
+    assert len(strict) == 1
+
+
+def test_empty_registry_counts():
+    registry = RuleRegistry()
+
+    assert len(registry) == 0
+    assert not registry.contains(Base)
+    assert registry.rule_count_for(Base) == 0
+
+
+def test_registry_counts():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_other_base))
+    registry.add_rule(as_rule(create_derived))
+
+    assert len(registry) == 3
+    assert registry.contains(Base)
+    assert registry.contains(Derived)
+    assert not registry.contains(int)
+    assert registry.rule_count_for(Base) == 2
+    assert registry.rule_count_for(Derived) == 1
+
+
+def test_iterate_rules():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_derived))
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_other_base))
+
+    assert list(registry) == [
+        as_rule(create_other_base),
+        as_rule(create_base),
+        as_rule(create_derived),
+    ]
+
+
+@rule(tags=["database", "primary"])
+def create_tagged_base() -> Base:
+    return Base(5)
+
+
+@rule(tags=["database"])
+def create_tagged_derived() -> Derived:
+    return Derived(6)
+
+
+def test_rules_with_tag():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_tagged_base))
+    registry.add_rule(as_rule(create_tagged_derived))
+
+    assert registry.rules_with_tag("database") == [
+        as_rule(create_tagged_base),
+        as_rule(create_tagged_derived),
+    ]
+    assert registry.rules_with_tag("primary") == [as_rule(create_tagged_base)]
+    assert registry.rules_with_tag("http") == []
+    assert as_rule(create_tagged_base).tags == {"database", "primary"}
+    assert as_rule(create_base).tags == frozenset()
+    assert "tags={database, primary}" in repr(as_rule(create_tagged_base))
+
+
+@rule
+def create_other_derived() -> Derived:
+    return Derived(4)
+
+
+def test_parent_registry():
+    parent = RuleRegistry()
+    parent.add_rule(as_rule(create_base))
+    parent.add_rule(as_rule(create_derived))
+    child = RuleRegistry(parent)
+    child.add_rule(as_rule(create_other_derived))
+
+    assert child.get_rules(Derived) == (as_rule(create_other_derived),)
+    assert child.get_rules(Base) == (
+        as_rule(create_base),
+        as_rule(create_other_derived),
+    )
+    assert parent.get_rules(Derived) == (as_rule(create_derived),)
+
+
+def test_parent_registry_solving():
+    parent = RuleRegistry()
+    parent.add_rule(as_rule(create_base))
+    parent.add_rule(as_rule(create_derived))
+    child = RuleRegistry(parent)
+    child.add_rule(as_rule(create_other_derived))
+    solver = Solver(child)
+
+    (derived,) = solver.solve_for(Derived)
+    (base,) = solver.solve_for(Annotated[Base, SolveSpecificity.Exact])
+
+    assert derived.rule == as_rule(create_other_derived)
+    assert base.rule == as_rule(create_base)
+
+
+def test_parent_registry_is_snapshotted():
+    parent = RuleRegistry()
+    parent.add_rule(as_rule(create_base))
+    child = RuleRegistry(parent)
+    parent.add_rule(as_rule(create_derived))
+    parent.remove_rule(as_rule(create_base))
+
+    assert child.get_rules(Derived) is None
+    assert child.get_rules(Base) == (as_rule(create_base),)
+    assert child.parent.get_rules(Base) == (as_rule(create_base),)
+
+
+def test_merge_parent_registry():
+    parent = RuleRegistry()
+    parent.add_rule(as_rule(create_derived))
+    child = RuleRegistry(parent, merge_parent=True)
+    child.add_rule(as_rule(create_other_derived))
+
+    assert child.get_rules(Derived) == (
+        as_rule(create_derived),
+        as_rule(create_other_derived),
+    )
+
+
+@dataclass(frozen=True)
+class Service:
+    base: Base
+
+
+@dataclass(frozen=True)
+class Missing:
+    value: int
+
+
+@rule
+def create_service(base: Base) -> Service:
+    return Service(base)
+
+
+@rule
+def create_dangling(missing: Missing) -> Service:
+    return Service(Base(missing.value))
+
+
+@rule
+def create_cyclic_base(service: Service) -> Base:
+    return Base(service.base.value)
+
+
+@rule
+def create_exact_service(
+    base: Annotated[Base, SolveSpecificity.Exact],
+) -> Service:
+    return Service(base)
+
+
+def test_dependents_of():
+    registry = RuleRegistry()
+    for r in (create_base, create_service, create_exact_service):
+        registry.add_rule(as_rule(r))
+
+    assert registry.dependents_of(Derived) == [as_rule(create_service)]
+    assert registry.dependents_of(Base) == [
+        as_rule(create_exact_service),
+        as_rule(create_service),
+    ]
+    assert registry.dependents_of(Service) == []
+
+
+def test_validate():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_base))
+    registry.add_rule(as_rule(create_service))
+    assert registry.validate() == []
+
+    registry.add_rule(as_rule(create_dangling))
+    (issue,) = registry.validate()
+    assert issue[:2] == (as_rule(create_dangling).canonical_name, "missing")
+    assert issue[2].inner_type is Missing
+    assert issue[3] == "missing"
+
+
+def test_validate_cycle():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(create_service))
+    registry.add_rule(as_rule(create_cyclic_base))
+
+    issues = registry.validate()
+    assert [issue[3] for issue in issues] == ["cycle"]
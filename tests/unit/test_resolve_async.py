@@ -0,0 +1,157 @@
+import asyncio
+from dataclasses import dataclass
+from typing import Annotated
+
+import pytest
+
+from composify.core import MetadataSet
+from composify.errors import AsyncQualifierError, SolveFailureError
+from composify.rules import rule
+from tests.utils import create_rule_solver
+
+
+calls: list[str] = []
+
+
+@dataclass(frozen=True)
+class Config:
+    value: int
+
+
+@dataclass(frozen=True)
+class Left:
+    value: int
+
+
+@dataclass(frozen=True)
+class Right:
+    value: int
+
+
+@dataclass(frozen=True)
+class Top:
+    value: int
+
+
+@rule
+async def create_config() -> Config:
+    calls.append("config")
+    await asyncio.sleep(0)
+    return Config(2)
+
+
+@rule
+def create_left(config: Config) -> Left:
+    return Left(config.value + 1)
+
+
+@rule
+async def create_right(config: Config) -> Right:
+    await asyncio.sleep(0)
+    return Right(config.value * 10)
+
+
+@rule
+def create_top(left: Left, right: Right) -> Top:
+    return Top(left.value + right.value)
+
+
+@pytest.mark.asyncio_cooperative
+async def test_resolve_mixed_graph():
+    calls.clear()
+    solver = create_rule_solver(
+        create_config, create_left, create_right, create_top
+    )
+
+    result = await solver.resolve_async(Top)
+
+    assert result == Top(23)
+    assert calls == ["config"]
+
+
+@pytest.mark.asyncio_cooperative
+async def test_resolve_sync_depending_on_async():
+    solver = create_rule_solver(create_config, create_left)
+
+    assert await solver.resolve_async(Left) == Left(3)
+
+
+@pytest.mark.asyncio_cooperative
+async def test_resolve_no_solution():
+    solver = create_rule_solver(create_left)
+
+    with pytest.raises(SolveFailureError):
+        await solver.resolve_async(Left)
+
+
+@dataclass(frozen=True)
+class Zone:
+    name: str
+
+
+@dataclass(frozen=True)
+class InZone:
+    name: str
+
+    async def qualify(self, attributes: MetadataSet) -> bool:
+        await asyncio.sleep(0)
+        calls.append(f"qualify {self.name}")
+        return Zone(self.name) in attributes.get_all(Zone)
+
+
+@dataclass(frozen=True)
+class Store:
+    zone: str
+
+
+@rule
+def create_eu_store() -> Annotated[Store, Zone("eu")]:
+    return Store("eu")
+
+
+@rule
+async def create_us_store() -> Annotated[Store, Zone("us")]:
+    await asyncio.sleep(0)
+    return Store("us")
+
+
+@dataclass(frozen=True)
+class Report:
+    store: Store
+
+
+@rule
+def create_report(store: Annotated[Store, InZone("us")]) -> Report:
+    return Report(store)
+
+
+@pytest.mark.asyncio_cooperative
+async def test_resolve_async_qualifier():
+    calls.clear()
+    solver = create_rule_solver(
+        create_eu_store, create_us_store, create_report
+    )
+
+    eu = await solver.resolve_async(Annotated[Store, InZone("eu")])
+    assert eu == Store("eu")
+    # Each store is qualified once, the results are reused by later solves.
+    assert sorted(calls) == ["qualify eu", "qualify eu"]
+
+    calls.clear()
+    assert await solver.resolve_async(Report) == Report(Store("us"))
+    assert sorted(calls) == ["qualify us", "qualify us"]
+
+
+@pytest.mark.asyncio_cooperative
+async def test_resolve_async_qualifier_no_solution():
+    solver = create_rule_solver(create_eu_store)
+
+    with pytest.raises(SolveFailureError):
+        await solver.resolve_async(Annotated[Store, InZone("us")])
+
+
+def test_async_qualifier_in_sync_solve():
+    solver = create_rule_solver(create_eu_store)
+
+    with pytest.raises(AsyncQualifierError, match="resolve_async"):
+        solver.solve_for(Annotated[Store, InZone("eu")])
@@ -1,8 +1,9 @@
 from dataclasses import dataclass
+from functools import partial
 
 import pytest
 
-from composify.core import SolveSpecificity
+from composify.core import RuleRegistry, SolveSpecificity
 from composify.rules import as_rule, collect_rules, rule
 
 
@@ -78,3 +79,106 @@ def test_dependency_qualifiers():
     for d in _rule.dependencies:
         assert d.typing.solve_parameter.specificity == SolveSpecificity.Exact
         assert d.typing.solve_parameter.specificity == SolveSpecificity.Exact
+
+
+def create_prefixed(prefix: str, param: Param) -> Result:
+    return Result(prefix + param.value)
+
+
+def test_partial_rule():
+    _rule = as_rule(rule(partial(create_prefixed, prefix="kw:")))
+    assert [d.name for d in _rule.dependencies] == ["param"]
+    assert _rule.output_type.inner_type == Result
+    assert not _rule.is_async
+    assert _rule.function(param=Param("a")) == Result("kw:a")
+
+    _rule = as_rule(rule(partial(create_prefixed, "pos:")))
+    assert [d.name for d in _rule.dependencies] == ["param"]
+    assert _rule.function(param=Param("a")) == Result("pos:a")
+
+
+class Factory:
+    def __init__(self, prefix: str) -> None:
+        self.prefix = prefix
+
+    def create(self, param: Param) -> Result:
+        return Result(self.prefix + param.value)
+
+    async def create_async(self, param: Param) -> Result:
+        return Result(self.prefix + param.value)
+
+
+def test_bound_method_rule():
+    _rule = as_rule(rule(Factory("method:").create))
+    assert [d.name for d in _rule.dependencies] == ["param"]
+    assert _rule.output_type.inner_type == Result
+    assert not _rule.is_async
+    assert _rule.function(param=Param("a")) == Result("method:a")
+
+    _rule = as_rule(rule(Factory("method:").create_async))
+    assert [d.name for d in _rule.dependencies] == ["param"]
+    assert _rule.is_async
+
+
+def test_rule_source_location():
+    _rule = as_rule(example_sync_rule)
+    assert _rule.module == __name__
+    assert _rule.qualname == "example_sync_rule"
+    assert _rule.lineno == example_sync_rule.__code__.co_firstlineno
+    assert f"defined_at={__name__}:{_rule.lineno}" in repr(_rule)
+
+
+def test_partial_rule_source_location():
+    _rule = as_rule(rule(partial(create_prefixed, prefix="kw:")))
+    assert _rule.module == __name__
+    assert _rule.qualname == "create_prefixed"
+    assert _rule.lineno == create_prefixed.__code__.co_firstlineno
+
+    _rule = as_rule(rule(Factory("method:").create))
+    assert _rule.module == __name__
+    assert _rule.qualname == "Factory.create"
+    assert _rule.lineno == Factory.create.__code__.co_firstlineno
+
+
+def test_rule_with_priority():
+    _rule = as_rule(example_sync_rule)
+    updated = _rule.with_priority(5)
+    assert updated.priority == 5
+    assert _rule.priority == 0
+    assert updated.function is _rule.function
+    assert updated.canonical_name == _rule.canonical_name
+    assert updated.output_type == _rule.output_type
+    assert updated.dependencies == _rule.dependencies
+    assert updated != _rule
+    assert updated.with_priority(0) == _rule
+
+
+def test_rule_with_tags():
+    _rule = as_rule(example_sync_rule)
+    updated = _rule.with_tags(["cache"])
+    assert updated.tags == frozenset({"cache"})
+    assert _rule.tags == frozenset()
+    assert updated.function is _rule.function
+    assert updated.priority == _rule.priority
+    assert updated.with_canonical_name("renamed").canonical_name == "renamed"
+
+
+@rule
+def example_param() -> Param:
+    return Param(1)
+
+
+@rule
+def example_optional_param(param: Param | None) -> Result:
+    return Result(param.value if param else 0)
+
+
+def test_rule_is_satisfiable_by():
+    registry = RuleRegistry()
+    sync_rule = as_rule(example_sync_rule)
+    assert not sync_rule.is_satisfiable_by(registry)
+    assert as_rule(example_optional_param).is_satisfiable_by(registry)
+    assert as_rule(example_param).is_satisfiable_by(registry)
+
+    registry.add_rule(as_rule(example_param))
+    assert sync_rule.is_satisfiable_by(registry)
@@ -1,21 +1,39 @@
+import functools
+import io
+import threading
+from concurrent.futures import ThreadPoolExecutor
 from dataclasses import dataclass
 from typing import Annotated
 
 import pytest
 
 from composify.core import (
+    First,
+    MetadataSet,
+    ResolutionOrder,
+    Rule,
     RuleRegistry,
+    Solution,
     SolutionArgsCollection,
     SolveCardinality,
+    SolveParameter,
     Solver,
+    SolveSpecificity,
+    Strict,
+    TypeInfo,
 )
 from composify.errors import (
+    AmbiguousSingleError,
     CyclicDependencyError,
     NoSolutionError,
     NotExclusiveError,
     SolveFailureError,
+    SolveTimeoutError,
+    TooManyPermutationsError,
+    TooManySolutionsError,
 )
 from composify.rules import as_rule, collect_rules, rule
+from tests.utils import create_rule_solver
 
 
 @dataclass
@@ -80,6 +98,19 @@ def test_no_solution():
     exc.value.contains(NoSolutionError)
 
 
+def test_no_solution_traces_rule():
+    solver = create_rule_solver(example_b)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(B)
+    error = next(e for e in exc.value.errors if isinstance(e, NoSolutionError))
+    (root, dependency) = error.traces
+    assert root[2] == as_rule(example_b).canonical_name
+    assert dependency[0] == "a"
+    assert dependency[2] is None
+    assert f"by {as_rule(example_b).canonical_name}" in str(exc.value)
+
+
 @rule
 def example_a2() -> A:
     return A(5)
@@ -94,3 +125,977 @@ def test_not_exclusive():
     with pytest.raises(SolveFailureError) as exc:
         solver.solve_for(Annotated[A, SolveCardinality.Exclusive])
     assert exc.value.contains(NotExclusiveError)
+
+
+def test_not_exclusive_conflicting_rules():
+    solver = create_rule_solver(example_a, example_a2)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(Annotated[A, SolveCardinality.Exclusive])
+    (error,) = exc.value.errors
+    names = {as_rule(example_a).canonical_name}
+    names.add(as_rule(example_a2).canonical_name)
+    assert set(error.conflicting_rules) == names
+    for name in names:
+        assert name in str(error)
+    assert str(error.target) in str(error)
+
+
+@dataclass
+class Leaf:
+    value: int
+
+
+@dataclass
+class Missing:
+    value: int
+
+
+@dataclass
+class Expensive:
+    value: int
+
+
+@dataclass
+class Target:
+    value: int
+
+
+class CountingQualifier:
+    def __init__(self) -> None:
+        self.calls = 0
+
+    def qualify(self, attributes: MetadataSet) -> bool:
+        self.calls += 1
+        return True
+
+
+counting_qualifier = CountingQualifier()
+
+
+@rule
+def example_leaf() -> Leaf:
+    return Leaf(1)
+
+
+@rule
+def example_expensive(
+    leaf: Annotated[Leaf, counting_qualifier],
+) -> Expensive:
+    return Expensive(leaf.value)
+
+
+@rule
+def example_target(a_expensive: Expensive, z_missing: Missing) -> Target:
+    return Target(a_expensive.value + z_missing.value)
+
+
+def test_unsolvable_dependency_fails_fast():
+    solver = create_rule_solver(example_leaf, example_expensive, example_target)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(Target)
+    assert exc.value.contains(NoSolutionError)
+    assert counting_qualifier.calls == 0
+
+
+@dataclass
+class Left:
+    value: int
+
+
+@dataclass
+class Right:
+    value: int
+
+
+@dataclass
+class Top:
+    value: int
+
+
+@rule
+def example_left(a: A) -> Left:
+    return Left(a.value)
+
+
+@rule
+def example_right(a: A) -> Right:
+    return Right(a.value)
+
+
+@rule
+def example_top(left: Left, right: Right) -> Top:
+    return Top(left.value + right.value)
+
+
+def test_build_plan():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (top,) = solver.solve_for(Top)
+    plan = [step.rule for step in top.build_plan()]
+
+    assert len(plan) == 4
+    assert plan.index(as_rule(example_a)) < plan.index(as_rule(example_left))
+    assert plan.index(as_rule(example_a)) < plan.index(as_rule(example_right))
+    assert plan.index(as_rule(example_left)) < plan.index(as_rule(example_top))
+    assert plan.index(as_rule(example_right)) < plan.index(as_rule(example_top))
+
+
+def test_to_dict():
+    solver = create_rule_solver(*rules)
+
+    (solution,) = solver.solve_for(B)
+
+    assert solution.to_dict() == {
+        "canonical_name": as_rule(example_b).canonical_name,
+        "output_type": str(solution.output_type),
+        "is_async": False,
+        "args": {
+            "a": {
+                "canonical_name": as_rule(example_a).canonical_name,
+                "output_type": str(as_rule(example_a).output_type),
+                "is_async": False,
+                "args": {},
+            },
+        },
+    }
+
+
+def test_to_dot():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (top,) = solver.solve_for(Top)
+    dot = top.to_dot()
+
+    assert dot.startswith("digraph Solution {")
+    assert dot.count("[label=") == 4 + 4
+    assert dot.count(as_rule(example_a).canonical_name) == 1
+    assert '[label="left"]' in dot
+    assert '[label="right"]' in dot
+    assert dot.count('[label="a"]') == 2
+
+
+def test_preview_depth():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (top,) = solver.solve_for(Top, max_preview_depth=1)
+    assert top.rule == as_rule(example_top)
+    assert not top.is_unresolved
+    left = top.args[0].solution
+    assert left.rule == as_rule(example_left)
+    assert not left.is_unresolved
+    (placeholder,) = left.args
+    assert placeholder.name == "a"
+    assert placeholder.solution.is_unresolved
+    assert placeholder.solution.output_type.inner_type is A
+    assert list(placeholder.solution.args) == []
+
+    (top,) = solver.solve_for(Top, max_preview_depth=0)
+    assert all(arg.solution.is_unresolved for arg in top.args)
+
+    (top,) = solver.solve_for(Top)
+    assert top.args[0].solution.args[0].solution.rule == as_rule(example_a)
+
+
+@rule(priority=1)
+def example_low() -> A:
+    return A(1)
+
+
+@rule(priority=5)
+def example_high() -> A:
+    return A(5)
+
+
+@rule(priority=3)
+def example_mid() -> A:
+    return A(3)
+
+
+def test_priority_order():
+    registry = RuleRegistry()
+    for r in (example_low, example_high, example_mid):
+        registry.add_rule(as_rule(r))
+
+    assert [r.priority for r in registry.get_rules(A)] == [5, 3, 1]
+
+    for _ in range(10):
+        solver = Solver(registry)
+        (solution,) = solver.solve_for(Annotated[A, SolveCardinality.Single])
+        assert solution.rule == as_rule(example_high)
+
+
+def test_memo_stats():
+    solver = create_rule_solver(*rules)
+
+    assert solver.memo_stats() == {"entries": {}, "total": 0}
+
+    solver.solve_for(B)
+    assert solver.memo_stats() == {
+        "entries": {TypeInfo.parse(A): 1, TypeInfo.parse(B): 1},
+        "total": 2,
+    }
+    assert solver.memo_stats(is_async=True) == {"entries": {}, "total": 0}
+
+
+@rule(priority=1)
+def other_a() -> A:
+    return A(7)
+
+
+def test_invalidate():
+    solver = create_rule_solver(example_a)
+    all_a = Annotated[A, SolveCardinality.Exhaustive]
+
+    assert len(solver.solve_for(all_a)) == 1
+    solver.add_rule(as_rule(other_a))
+    assert len(solver.solve_for(all_a)) == 1
+
+    solver.invalidate(all_a)
+    solutions = solver.solve_for(all_a)
+    assert [s.rule for s in solutions] == [as_rule(other_a), as_rule(example_a)]
+
+
+def test_invalidate_dependents():
+    solver = create_rule_solver(*rules)
+    solver.solve_for(B)
+
+    solver.invalidate(A)
+
+    assert solver.memo_stats() == {"entries": {}, "total": 0}
+
+
+@rule(priority=-1)
+def fallback_b() -> B:
+    return B(0)
+
+
+def test_invalidate_missing_dependency():
+    solver = create_rule_solver(example_b, fallback_b)
+    single_b = Annotated[B, SolveCardinality.Single]
+    (solution,) = solver.solve_for(single_b)
+    assert solution.rule == as_rule(fallback_b)
+
+    # B was memoized while A had no provider, its solutions do not contain A.
+    solver.add_rule(as_rule(example_a))
+    solver.invalidate(A)
+    (solution,) = solver.solve_for(single_b)
+    assert solution.rule == as_rule(example_b)
+
+
+def test_clear_cache():
+    solver = create_rule_solver(*rules)
+    solver.solve_for(B)
+
+    solver.clear_cache()
+
+    assert solver.memo_stats() == {"entries": {}, "total": 0}
+
+
+def test_unused_rules():
+    solver = create_rule_solver(*rules, other_a)
+
+    unused = solver.unused_rules([Annotated[A, SolveCardinality.Single]])
+
+    assert unused == [as_rule(example_a), as_rule(example_b)]
+
+
+def test_unused_rules_sharing_a_name():
+    provide_a = as_rule(example_a).with_canonical_name("shared")
+    provide_leaf = as_rule(example_leaf).with_canonical_name("shared")
+    solver = create_rule_solver(provide_a, provide_leaf)
+
+    assert solver.unused_rules([A]) == [provide_leaf]
+
+
+@rule
+def example_a_alternative() -> A:
+    return A(9)
+
+
+def test_equal_priority_tie_break():
+    single_a = Annotated[A, SolveCardinality.Single]
+    for order in (
+        (example_a, example_a_alternative),
+        (example_a_alternative, example_a),
+    ):
+        registry = RuleRegistry()
+        registry.add_rules(as_rule(r) for r in order)
+        for _ in range(3):
+            (solution,) = Solver(registry).solve_for(single_a)
+            assert solution.rule == as_rule(example_a)
+
+
+def test_solve_many():
+    registry = RuleRegistry()
+    for r in (example_a, example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+
+    results = Solver(registry).solve_many([Top, A, Left])
+
+    solver = Solver(registry)
+    assert results == [solver.solve_for(t) for t in (Top, A, Left)]
+
+
+def test_solve_many_failures():
+    solver = create_rule_solver(example_b, example_left)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_many([B, Left])
+    roots = {error.traces[0][1] for error in exc.value.errors}
+    assert roots == {TypeInfo.parse(B), TypeInfo.parse(Left)}
+
+
+def test_memo_capacity():
+    types = [type(f"Entry{i}", (), {}) for i in range(3)]
+    registry = RuleRegistry()
+    registry.add_rules(Rule(t, t.__name__, t, {}, 0, False) for t in types)
+    solver = Solver(registry, memo_capacity=2)
+    first, second, third = types
+
+    solver.solve_for(first)
+    solver.solve_for(second)
+    solver.solve_for(first)
+    solver.solve_for(third)
+
+    assert set(solver.memo_stats()["entries"]) == {
+        TypeInfo.parse(first),
+        TypeInfo.parse(third),
+    }
+
+
+def test_at_most_one_zero_match():
+    solver = create_rule_solver()
+
+    solutions = solver.solve_for(Annotated[A, SolveCardinality.AtMostOne])
+    assert solutions == []
+
+
+def test_at_most_one_single_match():
+    solver = create_rule_solver(example_a)
+
+    solutions = solver.solve_for(Annotated[A, SolveCardinality.AtMostOne])
+    assert [s.rule for s in solutions] == [as_rule(example_a)]
+
+
+def test_failure_named_fields():
+    solver = create_rule_solver(example_a, example_a2)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(Annotated[A, SolveCardinality.Exclusive])
+    assert isinstance(exc.value.errors, list)
+    (error,) = exc.value.errors
+    assert isinstance(error, NotExclusiveError)
+    assert isinstance(error.solutions, list)
+    assert {s.rule for s in error.solutions} == {
+        as_rule(example_a),
+        as_rule(example_a2),
+    }
+    assert isinstance(error.trace, list)
+    assert error.trace[0][0] == "__root__"
+    assert error.target.inner_type is A
+
+
+def test_at_most_one_multiple_match():
+    solver = create_rule_solver(example_a, example_a2)
+
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(Annotated[A, SolveCardinality.AtMostOne])
+    assert exc.value.contains(NotExclusiveError)
+    assert str(SolveCardinality.AtMostOne) == "?"
+
+
+def test_filter():
+    solver = create_rule_solver(example_low, example_high, example_mid)
+
+    solutions = solver.solve_for(
+        Annotated[A, SolveCardinality.Exhaustive],
+        filter=lambda solution: solution.rule.priority >= 3,
+    )
+    assert [s.rule.priority for s in solutions] == [5, 3]
+
+    # An exclusive target is satisfied once the other solutions are rejected.
+    (solution,) = solver.solve_for(
+        A, filter=lambda solution: solution.rule.priority < 3
+    )
+    assert solution.rule == as_rule(example_low)
+
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(A, filter=lambda solution: False)
+
+
+def test_solve_stats():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (solution,), stats = solver.solve_for_with_stats(Top)
+    assert solution.rule == as_rule(example_top)
+    # The shared dependency of the diamond is solved once.
+    assert stats.memo_hits == 1
+    assert stats.rules_evaluated == 4
+    assert stats.max_depth == 3
+    assert stats.permutations_generated == 3
+
+    _, stats = solver.solve_for_with_stats(Top)
+    assert stats.memo_hits == 1
+    assert stats.rules_evaluated == 0
+
+
+def test_preload():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (top, missing) = solver.preload([Top, B])
+    assert top == (TypeInfo.parse(Top), None)
+    assert missing[0] == TypeInfo.parse(B)
+    assert isinstance(missing[1], SolveFailureError)
+
+    _, stats = solver.solve_for_with_stats(Top)
+    assert stats.memo_hits == 1
+    assert stats.rules_evaluated == 0
+
+
+@rule
+def example_pair(
+    first: Annotated[A, SolveCardinality.Exhaustive],
+    second: Annotated[A, SolveCardinality.Exhaustive],
+) -> B:
+    return B(first.value + second.value)
+
+
+def test_max_permutations():
+    registry = RuleRegistry()
+    for r in (example_low, example_high, example_mid, example_pair):
+        registry.add_rule(as_rule(r))
+
+    solutions = Solver(registry, max_permutations=9).solve_for(
+        Annotated[B, SolveCardinality.Exhaustive]
+    )
+    assert len(solutions) == 9
+
+    with pytest.raises(SolveFailureError) as exc:
+        Solver(registry, max_permutations=4).solve_for(B)
+    assert exc.value.contains(TooManyPermutationsError)
+    error = next(
+        e for e in exc.value.errors if isinstance(e, TooManyPermutationsError)
+    )
+    assert error.count == 9
+
+
+def test_single_permutates_lazily():
+    solver = create_rule_solver(
+        example_low, example_high, example_mid, example_pair
+    )
+
+    (solution,), stats = solver.solve_for_with_stats(
+        Annotated[B, SolveCardinality.Single]
+    )
+    assert [arg.solution.rule for arg in solution.args] == [
+        as_rule(example_high),
+        as_rule(example_high),
+    ]
+    # Only the first of the 9 combinations is generated, the rest would be
+    # discarded by the cardinality.
+    assert stats.permutations_generated == 1
+
+    solutions, stats = solver.solve_for_with_stats(
+        Annotated[B, SolveCardinality.Exhaustive]
+    )
+    assert len(solutions) == 9
+    assert stats.permutations_generated == 9
+
+
+def test_solving_overlaps_across_threads():
+    def make_rule(value):
+        @rule
+        def produce() -> A:
+            return A(value)
+
+        # Registered either way, but solutions are told apart by the canonical
+        # name of their rules.
+        return as_rule(produce).with_canonical_name(f"produce_{value}")
+
+    registry = RuleRegistry()
+    for value in range(60):
+        registry.add_rule(make_rule(value))
+    registry.add_rule(as_rule(example_pair))
+    # The hook runs no bytecode, so the worker only yields the GIL where the
+    # solver releases it.
+    log = io.StringIO()
+    traced = Solver(registry, trace_hook=functools.partial(print, file=log))
+    solver = Solver(registry)
+    solving = threading.Event()
+    done = threading.Event()
+
+    def work():
+        solving.set()
+        solutions = traced.solve_for(Annotated[B, SolveCardinality.Exhaustive])
+        done.set()
+        return len(solutions)
+
+    with ThreadPoolExecutor(max_workers=1) as executor:
+        future = executor.submit(work)
+        solving.wait()
+        while not done.is_set():
+            solver.solve_for(Annotated[A, SolveCardinality.Exhaustive])
+            log.write("tick\n")
+        assert future.result() == 3600
+
+    lines = log.getvalue().splitlines()
+    first_push = next(i for i, line in enumerate(lines) if line[:4] == "push")
+    last_pop = max(i for i, line in enumerate(lines) if line[:3] == "pop")
+    assert "tick" in lines[first_push:last_pop]
+
+
+def test_solution_score():
+    low = Solution(as_rule(example_low))
+    high = Solution(as_rule(example_high))
+    pair = as_rule(example_pair)
+    assert Solution(pair, {"first": high, "second": low}).score() == 6
+    assert Solution(pair, {"first": low, "second": low}).score() == 2
+
+    solver = create_rule_solver(example_low, example_high, example_pair)
+    solutions = solver.solve_for(Annotated[B, SolveCardinality.Exhaustive])
+    assert [s.score() for s in solutions] == [10, 6, 6, 2]
+
+
+def test_solution_node_count():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+
+    (top,) = solver.solve_for(Top)
+    assert top.depth() == 3
+    assert top.node_count() == 5
+    assert top.node_count(unique=True) == 4
+
+
+def test_max_solutions_truncate():
+    solver = create_rule_solver(
+        example_low,
+        example_high,
+        example_mid,
+        max_solutions=2,
+        truncate_solutions=True,
+    )
+
+    solutions = solver.solve_for(Annotated[A, SolveCardinality.Exhaustive])
+    assert [s.rule for s in solutions] == [
+        as_rule(example_high),
+        as_rule(example_mid),
+    ]
+
+
+def test_max_solutions_error():
+    registry = RuleRegistry()
+    for r in (example_low, example_high, example_mid):
+        registry.add_rule(as_rule(r))
+    exhaustive = Annotated[A, SolveCardinality.Exhaustive]
+
+    with pytest.raises(SolveFailureError) as exc:
+        Solver(registry, max_solutions=2).solve_for(exhaustive)
+    assert exc.value.contains(TooManySolutionsError)
+    (error,) = exc.value.errors
+    assert error.max_solutions == 2
+
+    assert len(Solver(registry, max_solutions=3).solve_for(exhaustive)) == 3
+
+
+def test_trace_hook():
+    events = []
+
+    def hook(event, name, target):
+        events.append((event, name, target.inner_type))
+
+    solver = create_rule_solver(example_a, example_b, trace_hook=hook)
+    solver.solve_for(B)
+
+    assert events == [
+        ("push", "__root__", B),
+        ("push", "a", A),
+        ("pop", "a", A),
+        ("pop", "__root__", B),
+    ]
+
+
+def test_trace_hook_reentrant():
+    solved = []
+
+    def hook(event, name, target):
+        if event == "push" and target.inner_type is B:
+            solved.append(solver.solve_for(A))
+
+    solver = create_rule_solver(example_a, example_b, trace_hook=hook)
+
+    (solution,) = solver.solve_for(B)
+    assert solution.rule == as_rule(example_b)
+    assert [[s.rule for s in r] for r in solved] == [[as_rule(example_a)]]
+
+
+def test_duplicate_solutions_are_removed():
+    parent = RuleRegistry()
+    parent.add_rule(as_rule(example_a))
+    registry = RuleRegistry(parent, merge_parent=True)
+    registry.add_rule(as_rule(example_a))
+    registry.add_rule(as_rule(example_b))
+    solver = Solver(registry)
+
+    solutions = solver.solve_for(Annotated[B, SolveCardinality.Exhaustive])
+    assert [s.rule for s in solutions] == [as_rule(example_b)]
+    assert solutions[0].args[0].solution.rule == as_rule(example_a)
+
+
+def test_parallel_matches_sequential():
+    registry = RuleRegistry()
+    for r in (
+        example_low,
+        example_high,
+        example_mid,
+        example_pair,
+        example_left,
+        example_right,
+        example_top,
+    ):
+        registry.add_rule(as_rule(r))
+    pair = Annotated[B, SolveCardinality.Exhaustive]
+
+    sequential = Solver(registry)
+    parallel = Solver(registry, parallel=True)
+    assert parallel.solve_for(pair) == sequential.solve_for(pair)
+    assert len(parallel.solve_for(pair)) == 9
+
+    registry = RuleRegistry()
+    for r in (example_a, example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+    sequential = Solver(registry)
+    parallel = Solver(registry, parallel=True)
+    assert parallel.solve_for(Top) == sequential.solve_for(Top)
+
+
+def test_parallel_failure_matches_sequential():
+    registry = RuleRegistry()
+    for r in (example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+
+    def failure(solver):
+        with pytest.raises(SolveFailureError) as exc:
+            solver.solve_for(Top)
+        return str(exc.value)
+
+    assert failure(Solver(registry, parallel=True)) == failure(
+        Solver(registry)
+    )
+
+
+def test_parallel_shares_budget_and_stats():
+    registry = RuleRegistry()
+    for r in (example_a, example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+
+    parallel = Solver(registry, parallel=True)
+    with pytest.raises(SolveFailureError) as exc:
+        parallel.solve_for(Top, max_visits=1)
+    assert exc.value.contains(SolveTimeoutError)
+    assert parallel.memo_stats()["total"] == 0
+
+    _, sequential_stats = Solver(registry).solve_for_with_stats(Top)
+    _, parallel_stats = parallel.solve_for_with_stats(Top)
+    assert parallel_stats.rules_evaluated >= sequential_stats.rules_evaluated
+    assert parallel_stats.max_depth == sequential_stats.max_depth
+
+
+def test_parallel_trace_hook():
+    def trace(parallel):
+        events = []
+
+        def hook(event, name, target):
+            events.append((event, name, target.inner_type))
+
+        solver = Solver(registry, trace_hook=hook, parallel=parallel)
+        solver.solve_for(Top)
+        return events
+
+    registry = RuleRegistry()
+    for r in (example_a, example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+
+    assert trace(parallel=True) == trace(parallel=False)
+
+
+def test_with_overrides():
+    registry = RuleRegistry()
+    registry.add_rule(as_rule(example_a))
+    registry.add_rule(as_rule(example_b))
+    solver = Solver(registry)
+    assert solver.solve_for(B)[0].args["a"].solution.rule == as_rule(example_a)
+
+    with solver.with_overrides([as_rule(example_a2)]) as overridden:
+        (solution,) = overridden.solve_for(B)
+        assert solution.args["a"].solution.rule == as_rule(example_a2)
+
+    (solution,) = solver.solve_for(B)
+    assert solution.args["a"].solution.rule == as_rule(example_a)
+    assert registry.get_rules(A) == (as_rule(example_a),)
+
+
+def test_with_overrides_new_provider():
+    solver = create_rule_solver(example_b, fallback_b)
+    single_b = Annotated[B, SolveCardinality.Single]
+    assert solver.solve_for(single_b)[0].rule == as_rule(fallback_b)
+
+    with solver.with_overrides([as_rule(example_a)]) as overridden:
+        (solution,) = overridden.solve_for(single_b)
+        assert solution.rule == as_rule(example_b)
+        # Added to the override registry, discarded on exit.
+        overridden.add_rule(as_rule(example_leaf))
+        assert overridden.solve_first(Leaf) is not None
+
+    assert solver.solve_for(single_b)[0].rule == as_rule(fallback_b)
+    assert solver.solve_first(Leaf) is None
+
+
+def test_dependency_graph():
+    solver = create_rule_solver(
+        example_a, example_left, example_right, example_top
+    )
+    graph = solver.dependency_graph(Top)
+
+    top, left, right, a = (TypeInfo.parse(t) for t in (Top, Left, Right, A))
+    assert graph.edges == {top: [left, right], left: [a], right: [a], a: []}
+    assert graph.cycles == []
+
+
+def test_dependency_graph_cycle():
+    graph = create_rule_solver(example_b, example_cyclic).dependency_graph(B)
+
+    a, b = TypeInfo.parse(A), TypeInfo.parse(B)
+    assert graph.edges == {b: [a], a: [b]}
+    assert graph.cycles == [(a, b)]
+
+
+def test_explain():
+    registry = RuleRegistry()
+    for r in (example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+    solver = Solver(registry)
+
+    report = solver.explain(Top)
+    assert report.target.inner_type is Top
+    assert report.rules[0].rule == as_rule(example_top).canonical_name
+
+    (leaf,) = report.leaves()
+    assert leaf.name == "a"
+    assert leaf.target.inner_type is A
+    (error,) = leaf.errors
+    assert isinstance(error, NoSolutionError)
+
+    registry.add_rule(as_rule(example_a))
+    assert Solver(registry).explain(Top) is None
+
+
+def test_concurrent_solving():
+    registry = RuleRegistry()
+    for r in (example_a, example_left, example_right, example_top):
+        registry.add_rule(as_rule(r))
+    solver = Solver(registry, memo_capacity=2)
+    targets = [Top, Left, Right, A]
+    expected = {t: Solver(registry).solve_for(t) for t in targets}
+
+    def work(index):
+        for i in range(50):
+            target = targets[(index + i) % len(targets)]
+            assert solver.solve_for(target) == expected[target]
+            if i % 10 == 0:
+                solver.clear_cache()
+        return index
+
+    with ThreadPoolExecutor(max_workers=8) as executor:
+        assert sorted(executor.map(work, range(8))) == list(range(8))
+
+
+@rule(priority=4)
+def example_highest() -> A:
+    return A(4)
+
+
+@rule(priority=2)
+def example_low_mid() -> A:
+    return A(2)
+
+
+def test_first_n():
+    solver = create_rule_solver(
+        example_low, example_low_mid, example_mid, example_highest, example_high
+    )
+
+    solutions = solver.solve_for(Annotated[A, First(2)])
+    assert [s.rule for s in solutions] == [
+        as_rule(example_high),
+        as_rule(example_highest),
+    ]
+    assert TypeInfo.parse(Annotated[A, First(2)]).solve_parameter.limit == 2
+    assert str(TypeInfo.parse(Annotated[A, First(2)])).endswith("(+2)")
+
+
+def test_first_zero():
+    with pytest.raises(ValueError):
+        First(0)
+
+
+def test_strict_single():
+    solver = create_rule_solver(example_low, other_a, example_mid)
+
+    (solution,) = solver.solve_for(Annotated[A, Strict()])
+    assert solution.rule == as_rule(example_mid)
+    strict = TypeInfo.parse(Annotated[A, Strict()]).solve_parameter
+    assert strict.cardinality == SolveCardinality.Single
+    assert strict.strict
+
+
+def test_strict_single_tie():
+    registry = RuleRegistry()
+    for r in (example_low, other_a):
+        registry.add_rule(as_rule(r))
+
+    (solution,) = Solver(registry).solve_for(
+        Annotated[A, SolveCardinality.Single]
+    )
+    assert solution.rule.priority == 1
+
+    with pytest.raises(SolveFailureError) as exc:
+        Solver(registry).solve_for(Annotated[A, Strict()])
+    (error,) = exc.value.errors
+    assert isinstance(error, AmbiguousSingleError)
+    assert sorted(error.conflicting_rules) == sorted(
+        [as_rule(example_low).canonical_name, as_rule(other_a).canonical_name]
+    )
+
+
+@rule(priority=3)
+def preferred_a() -> Annotated[A, "preferred"]:
+    return A(2)
+
+
+def test_strict_single_prefer_attributes():
+    strict = Annotated[A, Strict()]
+    solver = create_rule_solver(preferred_a, example_high)
+    (solution,) = solver.solve_for(strict, prefer_attributes=["preferred"])
+    assert solution.rule == as_rule(example_high)
+
+    solver = create_rule_solver(example_low, preferred_a, example_mid)
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(strict, prefer_attributes=["preferred"])
+    (error,) = exc.value.errors
+    assert isinstance(error, AmbiguousSingleError)
+    assert sorted(error.conflicting_rules) == sorted(
+        [
+            as_rule(preferred_a).canonical_name,
+            as_rule(example_mid).canonical_name,
+        ]
+    )
+
+
+@rule
+def direct_b() -> B:
+    return B(0)
+
+
+def test_breadth_first_order():
+    registry = RuleRegistry()
+    for r in (example_high, example_b, direct_b):
+        registry.add_rule(as_rule(r))
+    exhaustive = Annotated[B, SolveCardinality.Exhaustive]
+
+    solutions = Solver(registry).solve_for(exhaustive)
+    assert [s.rule for s in solutions] == [
+        as_rule(example_b),
+        as_rule(direct_b),
+    ]
+
+    solver = Solver(registry, resolution_order=ResolutionOrder.BreadthFirst)
+    solutions = solver.solve_for(exhaustive)
+    assert [s.depth() for s in solutions] == [1, 2]
+    assert solutions[0].rule == as_rule(direct_b)
+    assert [s.rule for s in solver.solve_for(Annotated[B, First(1)])] == [
+        as_rule(direct_b)
+    ]
+
+
+def test_solve_first():
+    solver = create_rule_solver(example_a)
+    solution = solver.solve_first(A)
+    assert solution is not None
+    assert solution.rule == as_rule(example_a)
+
+    assert solver.solve_first(B) is None
+
+
+def test_solve_first_many_candidates():
+    solver = create_rule_solver(example_low, example_high, example_mid)
+    with pytest.raises(SolveFailureError):
+        solver.solve_for(A)
+
+    solution = solver.solve_first(A)
+    assert solution is not None
+    assert solution.rule == as_rule(example_high)
+    exhaustive = Annotated[A, SolveCardinality.Exhaustive]
+    assert solver.solve_first(exhaustive).rule == as_rule(example_high)
+
+
+def test_set_policy():
+    solver = create_rule_solver(
+        example_low, example_high, example_mid, example_pair
+    )
+    exhaustive_b = Annotated[B, SolveCardinality.Exhaustive]
+    assert len(solver.solve_for(exhaustive_b)) == 9
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_for(A)
+    assert exc.value.contains(NotExclusiveError)
+
+    single = SolveParameter(
+        SolveSpecificity.AllowSubclass, SolveCardinality.Single
+    )
+    solver.set_policy(A, single)
+    # Applies to dependencies too, replacing the memoized solutions.
+    (solution,) = solver.solve_for(exhaustive_b)
+    assert solution.rule == as_rule(example_pair)
+    assert [arg.solution.rule for arg in solution.args] == [
+        as_rule(example_high),
+        as_rule(example_high),
+    ]
+    (solution,) = solver.solve_for(A)
+    assert solution.rule == as_rule(example_high)
+
+    solver.set_policy(A, None)
+    assert len(solver.solve_for(exhaustive_b)) == 9
+
+
+def test_solve_first_with_policy():
+    solver = create_rule_solver(example_low, example_high, example_mid)
+    solver.set_policy(
+        A,
+        SolveParameter(
+            SolveSpecificity.AllowSubclass, SolveCardinality.Exhaustive
+        ),
+    )
+    assert solver.solve_first(A).rule == as_rule(example_high)
+
+    # The policy overrides the single cardinality of solve_first.
+    solver.set_policy(
+        A,
+        SolveParameter(
+            SolveSpecificity.AllowSubclass, SolveCardinality.Exclusive
+        ),
+    )
+    with pytest.raises(SolveFailureError) as exc:
+        solver.solve_first(A)
+    assert exc.value.contains(NotExclusiveError)
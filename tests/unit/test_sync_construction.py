@@ -118,3 +118,41 @@ def test_cached_construct():
         assert result.value == expected_result
 
     assert counter.execution == 5
+
+
+def test_solution_build():
+    plan = solution(
+        quintuple,
+        param=solution(double, param=static(Value(5))),
+    )
+    assert plan.build() == Value(50)
+
+    with pytest.raises(AsyncSolutionError):
+        solution(double, param=solution(async_value)).build()
+
+
+@dataclass(frozen=True)
+class Pair:
+    left: Value
+    right: Value
+
+
+@rule
+def create_pair(left: Value, right: Value) -> Pair:
+    return Pair(left, right)
+
+
+def test_solution_build_shared_and_overrides():
+    counter = ExecutionCounter()
+    double_ = counter(double)
+    shared = solution(double_, param=static(Value(5)))
+
+    plan = solution(create_pair, left=shared, right=shared)
+    result = plan.build()
+    assert result == Pair(Value(10), Value(10))
+    assert result.left is result.right
+    assert counter.execution == 1
+
+    result = plan.build({"right": Value(0)})
+    assert result == Pair(Value(10), Value(0))
+    assert counter.execution == 2
@@ -4,10 +4,10 @@ from composify.core import RuleRegistry, Solution, Solver
 from composify.rules import as_rule, static_rule, wraps_rule
 
 
-def create_rule_solver(*rules) -> Solver:
+def create_rule_solver(*rules, **kwargs) -> Solver:
     reg = RuleRegistry()
     reg.add_rules(as_rule(rule) for rule in rules)
-    return Solver(reg)
+    return Solver(reg, **kwargs)
 
 
 def solution(rule: Any, **kwargs: Solution) -> Solution: